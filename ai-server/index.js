@@ -1,7 +1,8 @@
 import express from 'express';
 import { OpenAI } from 'openai';
-import { Connection, PublicKey, Keypair, SystemProgram } from '@solana/web3.js';
-import { Program, AnchorProvider, web3 } from '@project-serum/anchor';
+import { Connection, PublicKey, Keypair, SystemProgram, Ed25519Program, SYSVAR_INSTRUCTIONS_PUBKEY } from '@solana/web3.js';
+import { Program, AnchorProvider, BN, web3 } from '@project-serum/anchor';
+import crypto from 'crypto';
 import axios from 'axios';
 import cors from 'cors';
 import dotenv from 'dotenv';
@@ -219,7 +220,7 @@ app.post("/finalize-prediction/:id", async (req, res) => {
 app.post("/execute-finalization/:id", async (req, res) => {
     try {
         const predictionId = req.params.id;
-        const { finalOutcome } = req.body;
+        const { finalOutcome, evidence } = req.body;
 
         if (finalOutcome === undefined) {
             return res.status(400).json({ error: "Final outcome is required" });
@@ -227,14 +228,31 @@ app.post("/execute-finalization/:id", async (req, res) => {
 
         const predictionDetails = await getPredictionDetailsViaPublicKey(predictionId);
 
+        // Sign (prediction | result | evidence hash | timestamp) with the
+        // registered oracle key; the program checks it via the ed25519 program
+        const evidenceHash = crypto.createHash('sha256').update(evidence ?? '').digest();
+        // Block time can be missing for a fresh slot; never sign a null timestamp
+        const blockTime = await connection.getBlockTime(await connection.getSlot("finalized"));
+        const timestamp = blockTime ?? Math.floor(Date.now() / 1000);
+        const message = Buffer.concat([
+            predictionDetails.publicKey.toBuffer(),
+            Buffer.from([finalOutcome ? 0 : 1]), // Borsh variant of PredictionResult::True / False
+            evidenceHash,
+            new BN(timestamp).toArrayLike(Buffer, 'le', 8),
+        ]);
+        const attestation = Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: wallet.secretKey,
+            message,
+        });
+
         const tx = await program.methods
-            .resolvePrediction({ [finalOutcome ? 'true' : 'false']: {} })
+            .resolveWithAttestation({ [finalOutcome ? 'true' : 'false']: {} }, [...evidenceHash], new BN(timestamp))
             .accounts({
                 marketState: MARKET_STATE_PUBKEY,
                 prediction: predictionDetails.publicKey,
-                admin: wallet.publicKey,
-                systemProgram: SystemProgram.programId,
+                instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
             })
+            .preInstructions([attestation])
             .rpc();
 
         await program.provider.connection.confirmTransaction(tx, "confirmed");
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use sol_contract::price_feed::*;
+
+declare_id!("9t6usiFib78PDQxWM2csXJVdvh9XYeVnqBXBU5CibMCt");
+
+/// Stands in for an oracle in local tests. Clients allocate a
+/// `PRICE_ACCOUNT_LEN` account owned by this program and then publish
+/// prices into it.
+#[program]
+pub mod mock_price_feed {
+    use super::*;
+
+    pub fn set_price(
+        ctx: Context<SetPrice>,
+        price: i64,
+        confidence: u64,
+        exponent: i32,
+        publish_time: i64,
+        status: u32,
+    ) -> Result<()> {
+        let mut data = ctx.accounts.feed.try_borrow_mut_data()?;
+        require!(
+            data.len() >= PRICE_ACCOUNT_LEN,
+            ErrorCode::AccountDidNotDeserialize
+        );
+
+        let mut write = |offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        write(MAGIC_OFFSET, &MAGIC.to_le_bytes());
+        write(VERSION_OFFSET, &VERSION.to_le_bytes());
+        write(ACCOUNT_TYPE_OFFSET, &ACCOUNT_TYPE_PRICE.to_le_bytes());
+        write(EXPONENT_OFFSET, &exponent.to_le_bytes());
+        write(TIMESTAMP_OFFSET, &publish_time.to_le_bytes());
+        write(PRICE_OFFSET, &price.to_le_bytes());
+        write(CONFIDENCE_OFFSET, &confidence.to_le_bytes());
+        write(STATUS_OFFSET, &status.to_le_bytes());
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    /// CHECK: raw feed bytes owned by this program
+    #[account(mut, owner = crate::ID)]
+    pub feed: UncheckedAccount<'info>,
+}
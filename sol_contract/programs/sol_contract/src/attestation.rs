@@ -0,0 +1,91 @@
+//! Oracle attestations checked through the ed25519 native program.
+//!
+//! The signature itself is verified by the runtime when the ed25519
+//! instruction executes; here we only confirm that such an instruction is in
+//! the transaction and that it covers the expected key and message.
+
+use crate::{PredictionError, PredictionResult};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const PUBLIC_KEY_LEN: usize = 32;
+/// Instruction index the ed25519 program uses for "this instruction's data".
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// The bytes an oracle signs: `prediction | result | evidence_hash | timestamp`,
+/// with the result Borsh encoded and the timestamp little endian.
+pub fn message(
+    prediction: &Pubkey,
+    result: &PredictionResult,
+    evidence_hash: &[u8; 32],
+    timestamp: i64,
+) -> Result<Vec<u8>> {
+    let mut message = prediction.to_bytes().to_vec();
+    result.serialize(&mut message)?;
+    message.extend_from_slice(evidence_hash);
+    message.extend_from_slice(&timestamp.to_le_bytes());
+    Ok(message)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .ok_or(PredictionError::InvalidAttestation)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn slice(data: &[u8], offset: u16, len: usize) -> Result<&[u8]> {
+    let start = offset as usize;
+    data.get(start..start + len)
+        .ok_or_else(|| PredictionError::InvalidAttestation.into())
+}
+
+/// Requires the instruction right before the current one to be an ed25519
+/// verification of `message` by `signer`, with all data inline.
+pub fn verify(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, PredictionError::InvalidAttestation);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        ix.program_id == ed25519_program::ID && ix.accounts.is_empty(),
+        PredictionError::InvalidAttestation
+    );
+
+    let data = &ix.data;
+    require!(
+        data.first() == Some(&1),
+        PredictionError::InvalidAttestation
+    );
+    let offsets = SIGNATURE_OFFSETS_START;
+    require!(
+        data.len() >= offsets + SIGNATURE_OFFSETS_LEN,
+        PredictionError::InvalidAttestation
+    );
+    let signature_instruction = read_u16(data, offsets + 2)?;
+    let public_key_offset = read_u16(data, offsets + 4)?;
+    let public_key_instruction = read_u16(data, offsets + 6)?;
+    let message_offset = read_u16(data, offsets + 8)?;
+    let message_len = read_u16(data, offsets + 10)?;
+    let message_instruction = read_u16(data, offsets + 12)?;
+    require!(
+        signature_instruction == CURRENT_INSTRUCTION
+            && public_key_instruction == CURRENT_INSTRUCTION
+            && message_instruction == CURRENT_INSTRUCTION,
+        PredictionError::InvalidAttestation
+    );
+
+    require!(
+        slice(data, public_key_offset, PUBLIC_KEY_LEN)? == signer.as_ref(),
+        PredictionError::InvalidAttestation
+    );
+    require!(
+        slice(data, message_offset, message_len as usize)? == message,
+        PredictionError::InvalidAttestation
+    );
+    Ok(())
+}
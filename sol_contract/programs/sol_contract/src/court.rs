@@ -0,0 +1,205 @@
+//! Helpers for the juror court: panel draws and vote commitments.
+//!
+//! The panel seed is committed when a dispute opens, together with a draw
+//! slot a few slots ahead. The draw mixes the seed with the hash of that slot,
+//! so the opener cannot pick the panel by choosing when to reveal, and the
+//! slot leader never learns the seed.
+
+use crate::{JurorVote, PredictionError, PredictionResult};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+/// SlotHashes starts with a u64 entry count, followed by `(slot, hash)` pairs
+/// ordered newest first.
+const SLOT_HASHES_ENTRIES: usize = 8;
+const SLOT_HASH_ENTRY_LEN: usize = 8 + 32;
+/// How many recent slots SlotHashes keeps.
+pub const SLOT_HASHES_DEPTH: u64 = 512;
+/// Slots between opening a dispute and the slot whose hash seeds the draw.
+pub const DRAW_DELAY_SLOTS: u64 = 8;
+
+/// The hash a dispute opener commits to before the panel is drawn.
+pub fn seed_commitment(seed: &[u8; 32]) -> [u8; 32] {
+    hashv(&[seed]).to_bytes()
+}
+
+/// The hash a juror commits to: `result | salt | juror`.
+pub fn vote_commitment(
+    result: &PredictionResult,
+    salt: &[u8; 32],
+    juror: &Pubkey,
+) -> Result<[u8; 32]> {
+    let result = result.try_to_vec()?;
+    Ok(hashv(&[&result, salt, juror.as_ref()]).to_bytes())
+}
+
+/// Hash of the first produced slot at or after `slot`, read from SlotHashes
+/// data. Fails unless an older entry is still present, since otherwise the
+/// first produced slot may already have dropped out of the window.
+pub fn slot_hash_at(data: &[u8], slot: u64) -> Result<[u8; 32]> {
+    let count = data
+        .get(..SLOT_HASHES_ENTRIES)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(PredictionError::SeedSlotUnavailable)?;
+    let mut found = None;
+    for index in 0..count as usize {
+        let start = SLOT_HASHES_ENTRIES + index * SLOT_HASH_ENTRY_LEN;
+        let entry = data
+            .get(start..start + SLOT_HASH_ENTRY_LEN)
+            .ok_or(PredictionError::SeedSlotUnavailable)?;
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if entry_slot < slot {
+            return found.ok_or_else(|| PredictionError::SeedSlotUnavailable.into());
+        }
+        found = Some(entry[8..].try_into().unwrap());
+    }
+    err!(PredictionError::SeedSlotUnavailable)
+}
+
+/// Picks `size` distinct entries of `candidates`, driven by `seed`.
+pub fn draw(seed: &[u8; 32], mut candidates: Vec<usize>, size: usize) -> Result<Vec<usize>> {
+    require!(candidates.len() >= size, PredictionError::NotEnoughJurors);
+    let mut panel = Vec::with_capacity(size);
+    for round in 0..size as u64 {
+        let hash = hashv(&[seed, &round.to_le_bytes()]).to_bytes();
+        let roll = u64::from_le_bytes(hash[..8].try_into().unwrap());
+        let pick = (roll % candidates.len() as u64) as usize;
+        panel.push(candidates.swap_remove(pick));
+    }
+    Ok(panel)
+}
+
+/// The result revealed by more than half of a panel of `panel_size`, if any.
+/// Jurors who never reveal count against every result.
+pub fn majority(votes: &[PredictionResult], panel_size: usize) -> Option<PredictionResult> {
+    votes
+        .iter()
+        .copied()
+        .find(|candidate| votes.iter().filter(|vote| *vote == candidate).count() * 2 > panel_size)
+}
+
+/// Whether a panel seat keeps its stake and shares in the slashed ones.
+/// With a verdict only the majority is rewarded; without one every juror
+/// who revealed is, so only those who never revealed are slashed.
+pub fn rewarded(vote: &JurorVote, verdict: Option<PredictionResult>) -> bool {
+    match verdict {
+        Some(verdict) => vote.revealed && vote.result == verdict,
+        None => vote.revealed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_hashes(entries: &[(u64, u8)]) -> Vec<u8> {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, fill) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*fill; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn slot_hash_at_reads_the_requested_slot() {
+        let data = slot_hashes(&[(12, 3), (11, 2), (10, 1)]);
+        assert_eq!(slot_hash_at(&data, 11).unwrap(), [2; 32]);
+    }
+
+    #[test]
+    fn slot_hash_at_falls_forward_over_skipped_slots() {
+        let data = slot_hashes(&[(14, 3), (12, 2), (10, 1)]);
+        assert_eq!(slot_hash_at(&data, 11).unwrap(), [2; 32]);
+    }
+
+    #[test]
+    fn slot_hash_at_rejects_slots_not_yet_produced_or_expired() {
+        let data = slot_hashes(&[(12, 3), (11, 2), (10, 1)]);
+        assert!(slot_hash_at(&data, 13).is_err());
+        assert!(slot_hash_at(&data, 10).is_err());
+        assert!(slot_hash_at(&data, 5).is_err());
+    }
+
+    #[test]
+    fn draw_picks_distinct_candidates_deterministically() {
+        let seed = [7; 32];
+        let panel = draw(&seed, (0..20).collect(), 5).unwrap();
+        assert_eq!(panel.len(), 5);
+        for (index, pick) in panel.iter().enumerate() {
+            assert!(*pick < 20);
+            assert!(!panel[..index].contains(pick));
+        }
+        assert_eq!(panel, draw(&seed, (0..20).collect(), 5).unwrap());
+        assert_ne!(panel, draw(&[8; 32], (0..20).collect(), 5).unwrap());
+    }
+
+    #[test]
+    fn draw_needs_enough_candidates() {
+        assert!(draw(&[0; 32], vec![1, 2], 3).is_err());
+        assert_eq!(draw(&[0; 32], vec![4, 9], 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn majority_needs_more_than_half_of_the_panel() {
+        let yes = PredictionResult::True;
+        let no = PredictionResult::False;
+        assert!(majority(&[yes, yes, no], 3) == Some(yes));
+        assert!(majority(&[yes, no], 3).is_none());
+        // Two of five agreeing is not a majority even if nobody disagrees
+        assert!(majority(&[yes, yes], 5).is_none());
+        assert!(majority(&[yes, yes, yes], 5) == Some(yes));
+        assert!(majority(&[], 3).is_none());
+    }
+
+    fn seat(revealed: bool, result: PredictionResult) -> JurorVote {
+        JurorVote {
+            juror: Pubkey::new_unique(),
+            commitment: [0; 32],
+            committed: true,
+            revealed,
+            result,
+        }
+    }
+
+    #[test]
+    fn rewarded_backs_the_majority_when_there_is_a_verdict() {
+        let verdict = Some(PredictionResult::True);
+        assert!(rewarded(&seat(true, PredictionResult::True), verdict));
+        assert!(!rewarded(&seat(true, PredictionResult::False), verdict));
+        assert!(!rewarded(
+            &seat(false, PredictionResult::Undefined),
+            verdict
+        ));
+    }
+
+    #[test]
+    fn rewarded_slashes_only_non_revealers_without_a_verdict() {
+        assert!(rewarded(&seat(true, PredictionResult::True), None));
+        assert!(rewarded(&seat(true, PredictionResult::False), None));
+        assert!(!rewarded(&seat(false, PredictionResult::Undefined), None));
+    }
+
+    #[test]
+    fn vote_commitment_binds_result_salt_and_juror() {
+        let juror = Pubkey::new_unique();
+        let salt = [1; 32];
+        let commitment = vote_commitment(&PredictionResult::True, &salt, &juror).unwrap();
+        assert_eq!(
+            commitment,
+            vote_commitment(&PredictionResult::True, &salt, &juror).unwrap()
+        );
+        assert_ne!(
+            commitment,
+            vote_commitment(&PredictionResult::False, &salt, &juror).unwrap()
+        );
+        assert_ne!(
+            commitment,
+            vote_commitment(&PredictionResult::True, &[2; 32], &juror).unwrap()
+        );
+        assert_ne!(
+            commitment,
+            vote_commitment(&PredictionResult::True, &salt, &Pubkey::new_unique()).unwrap()
+        );
+    }
+}
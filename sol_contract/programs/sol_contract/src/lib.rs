@@ -1,4 +1,14 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+pub mod attestation;
+pub mod court;
+pub mod lmsr;
+pub mod price_feed;
 
 declare_id!("J4bMC3qvhsjSDJojvVGUt1tzvm6xzk6R2hhUnwDSzH7s");
 
@@ -10,6 +20,324 @@ pub mod prediction_marketplace {
         let market_state = &mut ctx.accounts.market_state;
         market_state.admin = ctx.accounts.admin.key();
         market_state.next_prediction_id = 0;
+        market_state.guardian = ctx.accounts.admin.key();
+        market_state.paused = false;
+        market_state.pending_admin = Pubkey::default();
+        market_state.cancel_penalty_bps = 0;
+        market_state.cancel_cutoff = 0;
+        market_state.creation_bond = DEFAULT_CREATION_BOND;
+        market_state.proposal_bond = DEFAULT_PROPOSAL_BOND;
+        market_state.challenge_window = DEFAULT_CHALLENGE_WINDOW;
+        market_state.proposer_reward = 0;
+        market_state.oracle = Pubkey::default();
+        market_state.juror_stake = DEFAULT_JUROR_STAKE;
+        market_state.panel_size = DEFAULT_PANEL_SIZE;
+        market_state.court_period = DEFAULT_COURT_PERIOD;
+        market_state.court_bond = DEFAULT_COURT_BOND;
+        market_state.protocol_fee_bps = DEFAULT_PROTOCOL_FEE_BPS;
+        market_state.fee_recipient = market_state.key();
+        market_state.max_creator_fee_bps = DEFAULT_MAX_CREATOR_FEE_BPS;
+        market_state.oracle_program = Pubkey::default();
+        Ok(())
+    }
+
+    /// Configures optimistic resolution: the bond a proposer or disputer
+    /// posts, how long a proposal can be challenged, and the treasury-funded
+    /// reward for undisputed proposals.
+    pub fn set_oracle_config(
+        ctx: Context<UpdateMarketState>,
+        proposal_bond: u64,
+        challenge_window: i64,
+        proposer_reward: u64,
+    ) -> Result<()> {
+        require!(proposal_bond > 0, PredictionError::InvalidAmount);
+        require!(challenge_window > 0, PredictionError::InvalidDuration);
+
+        let market_state = &mut ctx.accounts.market_state;
+        market_state.proposal_bond = proposal_bond;
+        market_state.challenge_window = challenge_window;
+        market_state.proposer_reward = proposer_reward;
+
+        emit!(OracleConfigUpdatedEvent {
+            proposal_bond,
+            challenge_window,
+            proposer_reward,
+        });
+
+        Ok(())
+    }
+
+    /// Configures the juror court: the stake each drawn juror puts at risk,
+    /// how many jurors sit on a panel, how long each voting phase lasts, and
+    /// the bond a dispute opener forfeits by never revealing the seed.
+    pub fn set_court_config(
+        ctx: Context<UpdateMarketState>,
+        juror_stake: u64,
+        panel_size: u8,
+        court_period: i64,
+        court_bond: u64,
+    ) -> Result<()> {
+        require!(juror_stake > 0, PredictionError::InvalidAmount);
+        require!(
+            panel_size > 0 && panel_size as usize <= MAX_PANEL_SIZE,
+            PredictionError::InvalidPanelSize
+        );
+        require!(court_period > 0, PredictionError::InvalidDuration);
+
+        let market_state = &mut ctx.accounts.market_state;
+        market_state.juror_stake = juror_stake;
+        market_state.panel_size = panel_size;
+        market_state.court_period = court_period;
+        market_state.court_bond = court_bond;
+
+        emit!(CourtConfigUpdatedEvent {
+            juror_stake,
+            panel_size,
+            court_period,
+            court_bond,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the penalty and the cutoff before `end_time` that apply to bet
+    /// cancellations on markets created from now on.
+    pub fn set_cancel_policy(
+        ctx: Context<UpdateMarketState>,
+        penalty_bps: u16,
+        cutoff: i64,
+    ) -> Result<()> {
+        require!(penalty_bps <= BPS_DENOMINATOR, PredictionError::InvalidFee);
+        require!(cutoff >= 0, PredictionError::InvalidDuration);
+
+        let market_state = &mut ctx.accounts.market_state;
+        market_state.cancel_penalty_bps = penalty_bps;
+        market_state.cancel_cutoff = cutoff;
+
+        emit!(CancelPolicyUpdatedEvent {
+            penalty_bps,
+            cutoff,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the protocol fee taken from parimutuel pools on markets created
+    /// from now on, and where it is paid. Token fees go to a token account
+    /// owned by `fee_recipient`, or by the treasurer while fees stay in the
+    /// market treasury.
+    pub fn set_protocol_fee(
+        ctx: Context<UpdateMarketState>,
+        fee_bps: u16,
+        fee_recipient: Pubkey,
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_PROTOCOL_FEE_BPS, PredictionError::InvalidFee);
+        require!(
+            fee_recipient != Pubkey::default(),
+            PredictionError::InvalidFeeRecipient
+        );
+
+        let market_state = &mut ctx.accounts.market_state;
+        market_state.protocol_fee_bps = fee_bps;
+        market_state.fee_recipient = fee_recipient;
+
+        emit!(ProtocolFeeUpdatedEvent {
+            fee_bps,
+            fee_recipient,
+        });
+
+        Ok(())
+    }
+
+    /// Caps the share of a market's fee its creator can claim, in basis
+    /// points of the fee.
+    pub fn set_max_creator_fee(ctx: Context<UpdateMarketState>, max_fee_bps: u16) -> Result<()> {
+        require!(max_fee_bps <= BPS_DENOMINATOR, PredictionError::InvalidFee);
+
+        ctx.accounts.market_state.max_creator_fee_bps = max_fee_bps;
+
+        emit!(MaxCreatorFeeUpdatedEvent { max_fee_bps });
+
+        Ok(())
+    }
+
+    /// Lets a market's creator take a share of its fee, in basis points of
+    /// the fee. Like the fee itself it is fixed once the market takes stakes.
+    pub fn set_creator_fee(ctx: Context<ConfigureCreatorFee>, fee_bps: u16) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(
+            fee_bps <= ctx.accounts.market_state.max_creator_fee_bps,
+            PredictionError::InvalidFee
+        );
+        require!(
+            prediction.total_amount == 0,
+            PredictionError::MarketAlreadyFunded
+        );
+
+        prediction.creator_fee_bps = fee_bps;
+
+        emit!(CreatorFeeUpdatedEvent {
+            prediction_id: prediction.id,
+            fee_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Overrides the protocol fee of a single market before it takes stakes.
+    pub fn set_prediction_fee(ctx: Context<UpdatePredictionFee>, fee_bps: u16) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(fee_bps <= MAX_PROTOCOL_FEE_BPS, PredictionError::InvalidFee);
+        require!(
+            prediction.total_amount == 0,
+            PredictionError::MarketAlreadyFunded
+        );
+
+        prediction.protocol_fee_bps = fee_bps;
+
+        emit!(PredictionFeeUpdatedEvent {
+            prediction_id: prediction.id,
+            fee_bps,
+        });
+
+        Ok(())
+    }
+
+    /// First step of an admin rotation; the new key must call `accept_admin`.
+    pub fn propose_admin(ctx: Context<UpdateMarketState>, new_admin: Pubkey) -> Result<()> {
+        require!(
+            new_admin != Pubkey::default(),
+            PredictionError::InvalidAdmin
+        );
+        let market_state = &mut ctx.accounts.market_state;
+        market_state.pending_admin = new_admin;
+
+        emit!(AdminTransferProposedEvent {
+            admin: market_state.admin,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_admin_transfer(ctx: Context<UpdateMarketState>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        require!(
+            market_state.pending_admin != Pubkey::default(),
+            PredictionError::NoPendingAdmin
+        );
+        let pending_admin = market_state.pending_admin;
+        market_state.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferCancelledEvent {
+            admin: market_state.admin,
+            pending_admin,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        let previous_admin = market_state.admin;
+        market_state.admin = market_state.pending_admin;
+        market_state.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferredEvent {
+            previous_admin,
+            new_admin: market_state.admin,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the role bitmask held by `authority`. Passing zero revokes
+    /// every role while keeping the account.
+    pub fn set_roles(ctx: Context<SetRoles>, authority: Pubkey, roles: u8) -> Result<()> {
+        require!(roles & !ALL_ROLES == 0, PredictionError::InvalidRole);
+
+        let role = &mut ctx.accounts.role;
+        role.market = ctx.accounts.market_state.key();
+        role.authority = authority;
+        role.roles = roles;
+        role.bump = ctx.bumps.role;
+
+        emit!(RolesUpdatedEvent { authority, roles });
+
+        Ok(())
+    }
+
+    /// Moves accumulated protocol fees out of `MarketState`, keeping it rent exempt.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let market_state_info = ctx.accounts.market_state.to_account_info();
+        transfer_lamports(
+            &market_state_info,
+            &ctx.accounts.authority.to_account_info(),
+            amount,
+        )?;
+        require!(
+            market_state_info.lamports() >= Rent::get()?.minimum_balance(MarketState::LEN),
+            PredictionError::InsufficientFunds
+        );
+
+        emit!(FeesWithdrawnEvent {
+            treasurer: ctx.accounts.authority.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_creation_bond(ctx: Context<UpdateMarketState>, bond: u64) -> Result<()> {
+        ctx.accounts.market_state.creation_bond = bond;
+
+        emit!(CreationBondUpdatedEvent { bond });
+
+        Ok(())
+    }
+
+    /// Registers the key whose ed25519 attestations can settle markets.
+    pub fn set_oracle(ctx: Context<UpdateMarketState>, oracle: Pubkey) -> Result<()> {
+        ctx.accounts.market_state.oracle = oracle;
+
+        emit!(OracleUpdatedEvent { oracle });
+
+        Ok(())
+    }
+
+    /// Sets the program whose price accounts markets may settle from. Until
+    /// it is set no feed can be bound.
+    pub fn set_oracle_program(
+        ctx: Context<UpdateMarketState>,
+        oracle_program: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.market_state.oracle_program = oracle_program;
+
+        emit!(OracleProgramUpdatedEvent { oracle_program });
+
+        Ok(())
+    }
+
+    pub fn set_guardian(ctx: Context<UpdateMarketState>, guardian: Pubkey) -> Result<()> {
+        ctx.accounts.market_state.guardian = guardian;
+
+        emit!(GuardianUpdatedEvent { guardian });
+
+        Ok(())
+    }
+
+    /// Halts or reopens every user-facing instruction across the marketplace.
+    /// Refunds for voided markets stay available while paused.
+    pub fn set_global_pause(ctx: Context<SetGlobalPause>, paused: bool) -> Result<()> {
+        ctx.accounts.market_state.paused = paused;
+
+        emit!(GlobalPauseUpdatedEvent {
+            paused,
+            guardian: ctx.accounts.guardian.key(),
+        });
+
         Ok(())
     }
 
@@ -22,6 +350,7 @@ pub mod prediction_marketplace {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_prediction(
         ctx: Context<CreatePrediction>,
         description: String,
@@ -29,15 +358,48 @@ pub mod prediction_marketplace {
         tags: Vec<String>,
         prediction_type: u8,
         options_count: u8,
+        options: Vec<String>,
+        scalar_range: Option<ScalarRange>,
     ) -> Result<()> {
         let market_state = &mut ctx.accounts.market_state;
         let prediction = &mut ctx.accounts.prediction;
 
-        require!(
-            ctx.accounts.admin.key() == market_state.admin,
-            PredictionError::NotAuthorized
-        );
+        market_state.require_not_paused()?;
         require!(duration > 0, PredictionError::InvalidDuration);
+        match prediction_type {
+            PREDICTION_TYPE_BINARY => {
+                require!(options_count == 2, PredictionError::InvalidOptionsCount);
+                require!(options.is_empty(), PredictionError::InvalidOptionsCount);
+                require!(scalar_range.is_none(), PredictionError::InvalidScalarRange);
+            }
+            PREDICTION_TYPE_SCALAR => {
+                require!(options_count == 2, PredictionError::InvalidOptionsCount);
+                require!(options.is_empty(), PredictionError::InvalidOptionsCount);
+                let range = scalar_range.ok_or(PredictionError::InvalidScalarRange)?;
+                require!(
+                    range.lower_bound < range.upper_bound,
+                    PredictionError::InvalidScalarRange
+                );
+            }
+            PREDICTION_TYPE_CATEGORICAL => {
+                require!(
+                    (2..=MAX_OPTIONS).contains(&options_count),
+                    PredictionError::InvalidOptionsCount
+                );
+                require!(
+                    options.len() == options_count as usize,
+                    PredictionError::InvalidOptionsCount
+                );
+                require!(
+                    options
+                        .iter()
+                        .all(|option| option.len() <= MAX_OPTION_NAME_LENGTH),
+                    PredictionError::OptionNameTooLong
+                );
+                require!(scalar_range.is_none(), PredictionError::InvalidScalarRange);
+            }
+            _ => return Err(PredictionError::InvalidPredictionType.into()),
+        }
 
         let prediction_id = market_state.next_prediction_id;
         market_state.next_prediction_id = market_state
@@ -46,6 +408,8 @@ pub mod prediction_marketplace {
             .ok_or(PredictionError::Overflow)?;
 
         prediction.id = prediction_id;
+        prediction.market = market_state.key();
+        prediction.bump = ctx.bumps.prediction;
         prediction.state = PredictionState::Active;
         prediction.description = description;
         prediction.start_time = Clock::get()?.unix_timestamp;
@@ -53,6 +417,15 @@ pub mod prediction_marketplace {
             .start_time
             .checked_add(duration)
             .ok_or(PredictionError::Overflow)?;
+        prediction.cancel_penalty_bps = market_state.cancel_penalty_bps;
+        prediction.protocol_fee_bps = market_state.protocol_fee_bps;
+        prediction.creator_fee_bps = 0;
+        prediction.creator_fee = 0;
+        prediction.lmsr_surplus_withdrawn = false;
+        prediction.refund_basis = 0;
+        prediction.cancel_deadline = prediction
+            .end_time
+            .saturating_sub(market_state.cancel_cutoff);
         prediction.total_votes = 0;
         prediction.yes_votes = 0;
         prediction.no_votes = 0;
@@ -63,59 +436,117 @@ pub mod prediction_marketplace {
         prediction.prediction_type = prediction_type;
         prediction.options_count = options_count;
         prediction.tags = tags;
+        prediction.vault_bump = ctx.bumps.vault;
+        prediction.market_mode = MARKET_MODE_PARIMUTUEL;
+        prediction.option_votes = vec![0; options.len()];
+        prediction.option_amounts = vec![0; options.len()];
+        prediction.options = options;
+        if let Some(range) = scalar_range {
+            prediction.scalar_lower_bound = range.lower_bound;
+            prediction.scalar_upper_bound = range.upper_bound;
+        }
+        // Markets without a collateral mint are denominated in lamports
+        match &ctx.accounts.collateral_mint {
+            Some(mint) => {
+                require!(
+                    ctx.accounts.token_vault.is_some(),
+                    PredictionError::MissingTokenAccounts
+                );
+                require_supported_mint(&mint.to_account_info())?;
+                prediction.collateral_mint = mint.key();
+                prediction.collateral_decimals = mint.decimals;
+                prediction.token_vault_bump = ctx.bumps.token_vault;
+            }
+            None => {
+                prediction.collateral_mint = Pubkey::default();
+                prediction.collateral_decimals = NATIVE_DECIMALS;
+            }
+        }
+
+        // Wallets without the creator role post a bond, held in the vault
+        // next to the stakes until the market settles
+        let bond = if ctx.accounts.role.is_some() {
+            0
+        } else {
+            market_state.creation_bond
+        };
+        prediction.creator = ctx.accounts.authority.key();
+        prediction.creator_bond = bond;
+
+        // Keep the vault rent exempt so payouts can drain it down to zero stake
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        let deposit = Rent::get()?
+            .minimum_balance(0)
+            .checked_add(bond)
+            .ok_or(PredictionError::Overflow)?;
+        anchor_lang::system_program::transfer(cpi_context, deposit)?;
 
         emit!(PredictionCreatedEvent {
             prediction_id,
-            creator: ctx.accounts.admin.key(),
+            creator: ctx.accounts.authority.key(),
             description: prediction.description.clone(),
+            bond,
         });
 
         Ok(())
     }
 
     pub fn predict(ctx: Context<Predict>, verdict: bool, amount: u64) -> Result<()> {
-        let prediction = &mut ctx.accounts.prediction;
-        let user = &ctx.accounts.user;
-        let user_prediction = &mut ctx.accounts.user_prediction;
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
 
+        prediction.require_active()?;
+        // Scalar markets reuse the yes/no pools as their long/short sides
         require!(
-            prediction.state == PredictionState::Active,
-            PredictionError::PredictionNotActive
+            prediction.prediction_type == PREDICTION_TYPE_BINARY
+                || prediction.prediction_type == PREDICTION_TYPE_SCALAR,
+            PredictionError::InvalidPredictionType
         );
         require!(
-            Clock::get()?.unix_timestamp < prediction.end_time,
-            PredictionError::PredictionEnded
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL,
+            PredictionError::InvalidMarketMode
         );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < prediction.end_time, PredictionError::PredictionEnded);
         require!(amount > 0, PredictionError::InvalidAmount);
 
-        // Transfer SOL from user to market account
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: user.to_account_info(),
-                to: ctx.accounts.market_state.to_account_info(),
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_context, amount)?;
+        ctx.accounts.deposit(amount)?;
+        ctx.accounts.count_prediction()?;
+        let prediction = &mut ctx.accounts.prediction;
+        let user_prediction = &ctx.accounts.user_prediction;
 
-        prediction.total_votes = prediction
-            .total_votes
-            .checked_add(1)
-            .ok_or(PredictionError::Overflow)?;
-        if verdict {
-            prediction.yes_votes = prediction
-                .yes_votes
+        // Votes count bettors per side so a cancellation can unwind them
+        if user_prediction.amount == 0 {
+            prediction.total_votes = prediction
+                .total_votes
                 .checked_add(1)
                 .ok_or(PredictionError::Overflow)?;
+        }
+        if verdict {
+            if user_prediction.yes_amount == 0 {
+                prediction.yes_votes = prediction
+                    .yes_votes
+                    .checked_add(1)
+                    .ok_or(PredictionError::Overflow)?;
+            }
             prediction.yes_amount = prediction
                 .yes_amount
                 .checked_add(amount)
                 .ok_or(PredictionError::Overflow)?;
         } else {
-            prediction.no_votes = prediction
-                .no_votes
-                .checked_add(1)
-                .ok_or(PredictionError::Overflow)?;
+            if user_prediction.no_amount == 0 {
+                prediction.no_votes = prediction
+                    .no_votes
+                    .checked_add(1)
+                    .ok_or(PredictionError::Overflow)?;
+            }
             prediction.no_amount = prediction
                 .no_amount
                 .checked_add(amount)
@@ -126,11 +557,20 @@ pub mod prediction_marketplace {
             .total_amount
             .checked_add(amount)
             .ok_or(PredictionError::Overflow)?;
+        prediction.refund_basis = prediction
+            .refund_basis
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
 
-        user_prediction.user = user.key();
-        user_prediction.prediction_id = prediction.id;
-        user_prediction.amount = amount;
-        user_prediction.verdict = verdict;
+        let user = &ctx.accounts.user;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+        user_prediction.record_bet(user.key(), prediction.id, amount, now)?;
+        let side = if verdict {
+            &mut user_prediction.yes_amount
+        } else {
+            &mut user_prediction.no_amount
+        };
+        *side = side.checked_add(amount).ok_or(PredictionError::Overflow)?;
 
         emit!(PredictionMadeEvent {
             prediction_id: prediction.id,
@@ -142,546 +582,5725 @@ pub mod prediction_marketplace {
         Ok(())
     }
 
-    pub fn resolve_prediction(
-        ctx: Context<ResolvePrediction>,
-        result: PredictionResult,
-    ) -> Result<()> {
-        let market_state = &ctx.accounts.market_state;
-        let prediction = &mut ctx.accounts.prediction;
+    pub fn predict_option(ctx: Context<Predict>, option_index: u8, amount: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
 
+        prediction.require_active()?;
         require!(
-            ctx.accounts.admin.key() == market_state.admin,
-            PredictionError::NotAuthorized
+            prediction.prediction_type == PREDICTION_TYPE_CATEGORICAL,
+            PredictionError::InvalidPredictionType
         );
         require!(
-            prediction.state != PredictionState::Resolved,
-            PredictionError::PredictionAlreadyResolved
+            option_index < prediction.options_count,
+            PredictionError::InvalidOption
         );
         require!(
-            result != PredictionResult::Undefined,
-            PredictionError::InvalidResult
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL,
+            PredictionError::InvalidMarketMode
         );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < prediction.end_time, PredictionError::PredictionEnded);
+        require!(amount > 0, PredictionError::InvalidAmount);
 
-        prediction.result = result;
-        prediction.state = PredictionState::Resolved;
-
-        emit!(PredictionResolvedEvent {
-            prediction_id: prediction.id,
-            result,
-        });
-
-        Ok(())
-    }
+        ctx.accounts.deposit(amount)?;
+        ctx.accounts.count_prediction()?;
+        let prediction = &mut ctx.accounts.prediction;
 
-    pub fn submit_claim(ctx: Context<SubmitClaim>) -> Result<()> {
-        let prediction = &ctx.accounts.prediction;
-        let claims = &mut ctx.accounts.claims;
         let user_prediction = &ctx.accounts.user_prediction;
 
-        require!(
-            prediction.state == PredictionState::Resolved,
-            PredictionError::PredictionNotResolved
-        );
-
-        let is_winner = match prediction.result {
-            PredictionResult::True => user_prediction.verdict,
-            PredictionResult::False => !user_prediction.verdict,
-            PredictionResult::Undefined => return Err(PredictionError::InvalidResult.into()),
-        };
-
-        require!(is_winner, PredictionError::UserNotWinner);
-
-        let reward_amount = (user_prediction.amount * claims.reward_per_lamport) / 1_000_000;
+        let index = option_index as usize;
+        if user_prediction.amount == 0 {
+            prediction.total_votes = prediction
+                .total_votes
+                .checked_add(1)
+                .ok_or(PredictionError::Overflow)?;
+        }
+        if user_prediction
+            .option_amounts
+            .get(index)
+            .copied()
+            .unwrap_or(0)
+            == 0
+        {
+            prediction.option_votes[index] = prediction.option_votes[index]
+                .checked_add(1)
+                .ok_or(PredictionError::Overflow)?;
+        }
+        prediction.option_amounts[index] = prediction.option_amounts[index]
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        prediction.total_amount = prediction
+            .total_amount
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        prediction.refund_basis = prediction
+            .refund_basis
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
 
-        claims.pending_claims.push(Claim {
-            user: ctx.accounts.user.key(),
-            amount: reward_amount,
-            state: ClaimState::Pending,
-        });
+        let user = &ctx.accounts.user;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+        user_prediction.record_bet(user.key(), prediction.id, amount, now)?;
+        if user_prediction.option_amounts.is_empty() {
+            user_prediction.option_amounts = vec![0; prediction.options_count as usize];
+        }
+        user_prediction.option_amounts[index] = user_prediction.option_amounts[index]
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
 
-        emit!(ClaimSubmittedEvent {
+        emit!(OptionPredictionMadeEvent {
             prediction_id: prediction.id,
-            user: ctx.accounts.user.key(),
-            amount: reward_amount,
+            user: user.key(),
+            option_index,
+            amount,
         });
 
         Ok(())
     }
 
-    pub fn distribute_rewards(ctx: Context<DistributeRewards>) -> Result<()> {
-        let prediction = &mut ctx.accounts.prediction;
-        let market_state = &ctx.accounts.market_state;
+    /// Withdraws part or all of the stake on one side. For binary and scalar
+    /// markets `outcome` 0 is yes/long and 1 is no/short; for categorical
+    /// markets it is the option index. The penalty stays in the pool.
+    pub fn cancel_bet(ctx: Context<CancelBet>, outcome: u8, amount: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
 
-        // Ensure the prediction is resolved
+        let prediction: &mut Prediction = &mut ctx.accounts.prediction;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+
+        prediction.require_active()?;
         require!(
-            prediction.state == PredictionState::Resolved,
-            PredictionError::PredictionNotResolved
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL,
+            PredictionError::InvalidMarketMode
         );
-
-        // Ensure rewards haven't been distributed yet
         require!(
-            !prediction.rewards_distributed,
-            PredictionError::RewardsAlreadyDistributed
+            Clock::get()?.unix_timestamp < prediction.cancel_deadline,
+            PredictionError::CancelWindowClosed
+        );
+        require!(amount > 0, PredictionError::InvalidAmount);
+        require!(
+            outcome < prediction.options_count,
+            PredictionError::InvalidOption
         );
 
-        let total_pool = prediction.total_amount;
-        let admin_fee = (total_pool * 5) / 100; // 5% admin fee
-        let reward_pool = total_pool - admin_fee;
+        let index = outcome as usize;
+        let (stake, pool_amount, votes) =
+            if prediction.prediction_type == PREDICTION_TYPE_CATEGORICAL {
+                if user_prediction.option_amounts.is_empty() {
+                    return Err(PredictionError::InsufficientStake.into());
+                }
+                (
+                    &mut user_prediction.option_amounts[index],
+                    &mut prediction.option_amounts[index],
+                    &mut prediction.option_votes[index],
+                )
+            } else if outcome == 0 {
+                (
+                    &mut user_prediction.yes_amount,
+                    &mut prediction.yes_amount,
+                    &mut prediction.yes_votes,
+                )
+            } else {
+                (
+                    &mut user_prediction.no_amount,
+                    &mut prediction.no_amount,
+                    &mut prediction.no_votes,
+                )
+            };
+        require!(*stake >= amount, PredictionError::InsufficientStake);
+        *stake -= amount;
+        *pool_amount = pool_amount
+            .checked_sub(amount)
+            .ok_or(PredictionError::Overflow)?;
+        if *stake == 0 {
+            *votes = votes.saturating_sub(1);
+        }
 
-        // Transfer admin fee
-        let admin_transfer_instruction = anchor_lang::system_program::Transfer {
-            from: prediction.to_account_info(),
-            to: market_state.to_account_info(),
-        };
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                admin_transfer_instruction,
-                &[&[
-                    b"prediction",
-                    market_state.key().as_ref(),
-                    prediction.id.to_le_bytes().as_ref(),
-                    &[ctx.bumps.prediction],
-                ]],
-            ),
-            admin_fee,
+        user_prediction.amount = user_prediction
+            .amount
+            .checked_sub(amount)
+            .ok_or(PredictionError::Overflow)?;
+        if user_prediction.amount == 0 {
+            prediction.total_votes = prediction.total_votes.saturating_sub(1);
+        }
+
+        let penalty = mul_div(
+            amount,
+            prediction.cancel_penalty_bps as u64,
+            BPS_DENOMINATOR as u128,
         )?;
+        let refund = amount - penalty;
+        prediction.total_amount = prediction
+            .total_amount
+            .checked_sub(refund)
+            .ok_or(PredictionError::Overflow)?;
+        prediction.refund_basis = prediction
+            .refund_basis
+            .checked_sub(amount)
+            .ok_or(PredictionError::Overflow)?;
 
-        // Calculate winning amount
-        let winning_amount = if prediction.result == PredictionResult::True {
-            prediction.yes_amount
+        let prediction = &ctx.accounts.prediction;
+        let user = &ctx.accounts.user;
+        if prediction.is_native() {
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                user.to_account_info(),
+                &ctx.accounts.system_program,
+                refund,
+            )?;
         } else {
-            prediction.no_amount
-        };
+            let (mint, token_vault, token_program) = token_vault_accounts(
+                prediction,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.token_program,
+            )?;
+            let user_token_account = ctx
+                .accounts
+                .user_token_account
+                .as_ref()
+                .ok_or(PredictionError::MissingTokenAccounts)?;
+            transfer_from_token_vault(
+                prediction,
+                token_program,
+                mint,
+                token_vault,
+                user_token_account,
+                refund,
+            )?;
+        }
 
-        // Calculate reward per lamport
-        let reward_per_lamport = (reward_pool * 1_000_000) / winning_amount;
+        emit!(BetCancelledEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            outcome,
+            amount,
+            penalty,
+        });
 
-        prediction.reward_per_lamport = reward_per_lamport;
-        prediction.rewards_distributed = true;
+        Ok(())
+    }
 
-        emit!(RewardsDistributedEvent {
+    pub fn initialize_lmsr(ctx: Context<InitializeLmsr>, liquidity: u64) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        prediction.require_active()?;
+        require!(
+            prediction.prediction_type == PREDICTION_TYPE_BINARY
+                || prediction.prediction_type == PREDICTION_TYPE_CATEGORICAL,
+            PredictionError::InvalidPredictionType
+        );
+        require!(
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL && prediction.total_amount == 0,
+            PredictionError::InvalidMarketMode
+        );
+        require!(
+            prediction.is_native(),
+            PredictionError::UnsupportedCollateral
+        );
+        require!(liquidity > 0, PredictionError::InvalidLiquidity);
+
+        // The creator covers the market maker's worst-case loss of b * ln(n)
+        let subsidy = lmsr::subsidy(liquidity, prediction.options_count)?;
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, subsidy)?;
+
+        prediction.market_mode = MARKET_MODE_LMSR;
+        prediction.lmsr_liquidity = liquidity;
+        prediction.lmsr_shares = vec![0; prediction.options_count as usize];
+
+        emit!(LmsrInitializedEvent {
             prediction_id: prediction.id,
-            total_pool,
-            admin_fee,
-            reward_pool,
-            reward_per_lamport,
+            liquidity,
+            subsidy,
         });
 
         Ok(())
     }
 
-    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        let prediction = &ctx.accounts.prediction;
-        let user_prediction = &mut ctx.accounts.user_prediction;
+    pub fn buy_shares(
+        ctx: Context<TradeShares>,
+        outcome: u8,
+        shares: u64,
+        max_cost: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &mut ctx.accounts.prediction;
         let user = &ctx.accounts.user;
+        let user_prediction = &mut ctx.accounts.user_prediction;
 
-        // Ensure the prediction is resolved and rewards are distributed
+        prediction.require_active()?;
         require!(
-            prediction.state == PredictionState::Resolved,
-            PredictionError::PredictionNotResolved
+            prediction.market_mode == MARKET_MODE_LMSR,
+            PredictionError::InvalidMarketMode
         );
         require!(
-            prediction.rewards_distributed,
-            PredictionError::RewardsNotDistributed
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(
+            outcome < prediction.options_count,
+            PredictionError::InvalidOption
         );
+        require!(shares > 0, PredictionError::InvalidAmount);
 
-        // Ensure the user hasn't claimed their reward yet
+        let index = outcome as usize;
+        let mut book = prediction.lmsr_shares.clone();
+        book[index] = book[index]
+            .checked_add(shares)
+            .ok_or(PredictionError::Overflow)?;
+        let cost = lmsr::buy_cost(&prediction.lmsr_shares, &book, prediction.lmsr_liquidity)?;
+        require!(cost <= max_cost, PredictionError::SlippageExceeded);
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: user.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, cost)?;
+
+        prediction.lmsr_shares = book;
+        prediction.total_amount = prediction
+            .total_amount
+            .checked_add(cost)
+            .ok_or(PredictionError::Overflow)?;
+
+        if user_prediction.shares.is_empty() {
+            user_prediction.user = user.key();
+            user_prediction.prediction_id = prediction.id;
+            user_prediction.shares = vec![0; prediction.options_count as usize];
+        }
+        user_prediction.shares[index] = user_prediction.shares[index]
+            .checked_add(shares)
+            .ok_or(PredictionError::Overflow)?;
+        // Net spend on the book: reputation scores the payout against it and
+        // a voided market refunds it
+        user_prediction.amount = user_prediction
+            .amount
+            .checked_add(cost)
+            .ok_or(PredictionError::Overflow)?;
+        prediction.refund_basis = prediction
+            .refund_basis
+            .checked_add(cost)
+            .ok_or(PredictionError::Overflow)?;
+
+        emit!(SharesTradedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            outcome,
+            shares,
+            amount: cost,
+            is_buy: true,
+            price: lmsr::price(
+                &prediction.lmsr_shares,
+                prediction.lmsr_liquidity,
+                index,
+                REWARD_PRECISION
+            )?,
+        });
+
+        Ok(())
+    }
+
+    pub fn sell_shares(
+        ctx: Context<TradeShares>,
+        outcome: u8,
+        shares: u64,
+        min_proceeds: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &mut ctx.accounts.prediction;
+        let user = &ctx.accounts.user;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+
+        prediction.require_active()?;
         require!(
-            !user_prediction.reward_claimed,
-            PredictionError::RewardAlreadyClaimed
+            prediction.market_mode == MARKET_MODE_LMSR,
+            PredictionError::InvalidMarketMode
+        );
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(
+            outcome < prediction.options_count,
+            PredictionError::InvalidOption
         );
+        require!(shares > 0, PredictionError::InvalidAmount);
 
-        // Check if the user is a winner
-        let is_winner = match prediction.result {
-            PredictionResult::True => user_prediction.verdict,
-            PredictionResult::False => !user_prediction.verdict,
-            PredictionResult::Undefined => return Err(PredictionError::InvalidResult.into()),
-        };
-        require!(is_winner, PredictionError::UserNotWinner);
+        let index = outcome as usize;
+        let held = user_prediction.shares.get(index).copied().unwrap_or(0);
+        require!(held >= shares, PredictionError::InsufficientShares);
 
-        // Calculate the reward
-        let reward = (user_prediction.amount * prediction.reward_per_lamport) / 1_000_000;
+        let mut book = prediction.lmsr_shares.clone();
+        book[index] = book[index]
+            .checked_sub(shares)
+            .ok_or(PredictionError::Overflow)?;
+        let proceeds =
+            lmsr::sell_proceeds(&prediction.lmsr_shares, &book, prediction.lmsr_liquidity)?;
+        require!(proceeds >= min_proceeds, PredictionError::SlippageExceeded);
 
-        // Transfer the reward
-        let transfer_instruction = anchor_lang::system_program::Transfer {
-            from: prediction.to_account_info(),
-            to: user.to_account_info(),
-        };
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                transfer_instruction,
-                &[&[
-                    b"prediction",
-                    ctx.accounts.market_state.key().as_ref(),
-                    prediction.id.to_le_bytes().as_ref(),
-                    &[ctx.bumps.prediction],
-                ]],
-            ),
-            reward,
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            proceeds,
         )?;
 
-        // Mark the reward as claimed
-        user_prediction.reward_claimed = true;
+        prediction.lmsr_shares = book;
+        prediction.total_amount = prediction
+            .total_amount
+            .checked_sub(proceeds)
+            .ok_or(PredictionError::Overflow)?;
+        user_prediction.shares[index] = held - shares;
+        let recovered = proceeds.min(user_prediction.amount);
+        user_prediction.amount -= recovered;
+        prediction.refund_basis -= recovered;
 
-        emit!(RewardClaimedEvent {
+        emit!(SharesTradedEvent {
             prediction_id: prediction.id,
             user: user.key(),
-            amount: reward,
+            outcome,
+            shares,
+            amount: proceeds,
+            is_buy: false,
+            price: lmsr::price(
+                &prediction.lmsr_shares,
+                prediction.lmsr_liquidity,
+                index,
+                REWARD_PRECISION
+            )?,
         });
 
         Ok(())
     }
 
-    pub fn approve_claims(ctx: Context<ApproveClaims>, claim_indices: Vec<u64>) -> Result<()> {
-        let market_state = &ctx.accounts.market_state;
-        let prediction = &ctx.accounts.prediction;
-        let claims = &mut ctx.accounts.claims;
+    /// Returns what the LMSR maker kept to the market's creator once rewards
+    /// are distributed: the subsidy plus net trading revenue, minus one
+    /// lamport held back for every winning share, redeemed or not. In a
+    /// voided market the traders' refunds are held back instead.
+    pub fn withdraw_lmsr_surplus(ctx: Context<WithdrawLmsrSurplus>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let prediction = &mut ctx.accounts.prediction;
 
         require!(
-            ctx.accounts.admin.key() == market_state.admin,
-            PredictionError::NotAuthorized
+            prediction.market_mode == MARKET_MODE_LMSR,
+            PredictionError::InvalidMarketMode
+        );
+        require!(
+            prediction.state == PredictionState::Cancelled
+                || (prediction.state == PredictionState::Resolved
+                    && prediction.rewards_distributed),
+            PredictionError::RewardsNotDistributed
+        );
+        require!(
+            !prediction.lmsr_surplus_withdrawn,
+            PredictionError::SurplusAlreadyWithdrawn
         );
 
-        let mut total_approved_amount = 0;
+        let owed = if prediction.state == PredictionState::Cancelled {
+            prediction.refund_pool()?
+        } else {
+            prediction
+                .lmsr_shares
+                .get(prediction.winning_outcome()? as usize)
+                .copied()
+                .ok_or(PredictionError::InvalidResult)?
+        };
+        let surplus = lmsr::subsidy(prediction.lmsr_liquidity, prediction.options_count)?
+            .checked_add(prediction.total_amount)
+            .ok_or(PredictionError::Overflow)?
+            .checked_sub(owed)
+            .ok_or(PredictionError::Overflow)?;
+        prediction.lmsr_surplus_withdrawn = true;
 
-        for &index in claim_indices.iter() {
-            require!(
-                (index as usize) < claims.pending_claims.len(),
-                PredictionError::InvalidClaimIndex
-            );
-            let claim = &mut claims.pending_claims[index as usize];
-            require!(
-                claim.state == ClaimState::Pending,
-                PredictionError::ClaimNotPending
-            );
+        transfer_from_vault(
+            &ctx.accounts.prediction,
+            &ctx.accounts.vault,
+            ctx.accounts.creator.to_account_info(),
+            &ctx.accounts.system_program,
+            surplus,
+        )?;
 
-            claim.state = ClaimState::Approved;
-            total_approved_amount += claim.amount;
+        emit!(LmsrSurplusWithdrawnEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            creator: ctx.accounts.creator.key(),
+            amount: surplus,
+        });
 
-            emit!(ClaimApprovedEvent {
-                prediction_id: prediction.id,
-                user: claim.user,
-                amount: claim.amount,
-            });
-        }
+        Ok(())
+    }
+
+    pub fn initialize_pool(ctx: Context<InitializePool>, fee_bps: u16) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
 
-        // Transfer total approved amount to the claims account
-        let transfer_ctx = CpiContext::new(
+        prediction.require_active()?;
+        require!(
+            prediction.prediction_type == PREDICTION_TYPE_BINARY,
+            PredictionError::InvalidPredictionType
+        );
+        require!(
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL && prediction.total_amount == 0,
+            PredictionError::InvalidMarketMode
+        );
+        require!(
+            prediction.is_native(),
+            PredictionError::UnsupportedCollateral
+        );
+        require!(fee_bps <= MAX_POOL_FEE_BPS, PredictionError::InvalidFee);
+
+        prediction.market_mode = MARKET_MODE_CPMM;
+
+        pool.prediction = prediction.key();
+        pool.yes_reserve = 0;
+        pool.no_reserve = 0;
+        pool.total_shares = 0;
+        pool.fee_bps = fee_bps;
+        pool.fee_per_share = 0;
+        pool.total_cost = 0;
+        pool.collateral = 0;
+        pool.bump = ctx.bumps.pool;
+
+        emit!(PoolInitializedEvent {
+            prediction_id: prediction.id,
+            pool: pool.key(),
+            fee_bps,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_liquidity(ctx: Context<PoolTrade>, amount: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(amount > 0, PredictionError::InvalidAmount);
+
+        let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             anchor_lang::system_program::Transfer {
-                from: ctx.accounts.market_state.to_account_info(),
-                to: ctx.accounts.claims.to_account_info(),
+                from: user.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
             },
         );
-        anchor_lang::system_program::transfer(transfer_ctx, total_approved_amount)?;
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        position.pool = pool.key();
+        position.user = user.key();
+        let fees = pool.settle_fees(position)?;
+        pool.record_deposit(position, amount)?;
+        pool.record_withdrawal(position, fees)?;
+
+        // The deposit mints complete YES/NO sets. The pool keeps them in its
+        // current ratio and the surplus of the cheaper side goes to the LP.
+        let (shares, yes_added, no_added) = if pool.total_shares == 0 {
+            (amount, amount, amount)
+        } else {
+            let weight = pool.yes_reserve.max(pool.no_reserve) as u128;
+            let yes_added = mul_div(amount, pool.yes_reserve, weight)?;
+            let no_added = mul_div(amount, pool.no_reserve, weight)?;
+            let shares = mul_div(amount, pool.total_shares, weight)?;
+            (shares, yes_added, no_added)
+        };
+        require!(shares > 0, PredictionError::InvalidAmount);
+
+        pool.yes_reserve = pool
+            .yes_reserve
+            .checked_add(yes_added)
+            .ok_or(PredictionError::Overflow)?;
+        pool.no_reserve = pool
+            .no_reserve
+            .checked_add(no_added)
+            .ok_or(PredictionError::Overflow)?;
+        pool.total_shares = pool
+            .total_shares
+            .checked_add(shares)
+            .ok_or(PredictionError::Overflow)?;
+
+        position.yes_tokens = position
+            .yes_tokens
+            .checked_add(amount - yes_added)
+            .ok_or(PredictionError::Overflow)?;
+        position.no_tokens = position
+            .no_tokens
+            .checked_add(amount - no_added)
+            .ok_or(PredictionError::Overflow)?;
+        position.lp_shares = position
+            .lp_shares
+            .checked_add(shares)
+            .ok_or(PredictionError::Overflow)?;
+        position.fee_debt = pool.fee_debt_for(position.lp_shares)?;
+
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            fees,
+        )?;
+
+        emit!(LiquidityChangedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            amount,
+            shares,
+            fees,
+            is_deposit: true,
+        });
 
         Ok(())
     }
 
-}
+    pub fn remove_liquidity(ctx: Context<PoolTrade>, shares: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = admin, space = 8 + 32 + 8)]
-    pub market_state: Account<'info, MarketState>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let prediction = &ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
 
-#[derive(Accounts)]
-#[instruction(description: String, duration: i64, tags: Vec<String>, prediction_type: u8, options_count: u8)]
-pub struct CreatePrediction<'info> {
-    #[account(mut)]
-    pub market_state: Account<'info, MarketState>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 8 + 1 + 4 + description.len() + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + 4 + tags.len() * 32
-    )]
-    pub prediction: Account<'info, Prediction>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        prediction.require_active()?;
+        require!(shares > 0, PredictionError::InvalidAmount);
+        require!(
+            position.lp_shares >= shares,
+            PredictionError::InsufficientShares
+        );
 
-#[derive(Accounts)]
-#[instruction(verdict: bool, amount: u64)]
-pub struct Predict<'info> {
-    #[account(mut)]
-    pub market_state: Account<'info, MarketState>,
-    #[account(mut)]
-    pub prediction: Account<'info, Prediction>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + 32 + 8 + 8 + 1 + 1, // Adjust this if needed
-        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
-    pub user_prediction: Account<'info, UserPrediction>,
-    pub system_program: Program<'info, System>,
-}
-#[derive(Accounts)]
-pub struct ResolvePrediction<'info> {
-    pub market_state: Account<'info, MarketState>,
-    #[account(mut)]
-    pub prediction: Account<'info, Prediction>,
-    pub admin: Signer<'info>,
-}
+        let fees = pool.settle_fees(position)?;
+
+        let total_shares = pool.total_shares as u128;
+        let yes_removed = mul_div(pool.yes_reserve, shares, total_shares)?;
+        let no_removed = mul_div(pool.no_reserve, shares, total_shares)?;
+        pool.yes_reserve -= yes_removed;
+        pool.no_reserve -= no_removed;
+        pool.total_shares -= shares;
+        position.lp_shares -= shares;
+        position.fee_debt = pool.fee_debt_for(position.lp_shares)?;
+
+        // Merge matching YES/NO tokens back into collateral
+        let merged = yes_removed.min(no_removed);
+        position.yes_tokens = position
+            .yes_tokens
+            .checked_add(yes_removed - merged)
+            .ok_or(PredictionError::Overflow)?;
+        position.no_tokens = position
+            .no_tokens
+            .checked_add(no_removed - merged)
+            .ok_or(PredictionError::Overflow)?;
+
+        let amount = merged.checked_add(fees).ok_or(PredictionError::Overflow)?;
+        pool.record_withdrawal(position, amount)?;
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+
+        emit!(LiquidityChangedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            amount: merged,
+            shares,
+            fees,
+            is_deposit: false,
+        });
+
+        Ok(())
+    }
+
+    pub fn buy_outcome(
+        ctx: Context<PoolTrade>,
+        verdict: bool,
+        amount: u64,
+        min_tokens: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(amount > 0, PredictionError::InvalidAmount);
+        require!(
+            pool.total_shares > 0,
+            PredictionError::InsufficientLiquidity
+        );
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: user.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let fee = mul_div(amount, pool.fee_bps as u64, BPS_DENOMINATOR as u128)?;
+        let invested = amount - fee;
+        pool.collect_fee(fee)?;
+
+        let tokens = pool.buy(verdict, invested)?;
+        require!(tokens >= min_tokens, PredictionError::SlippageExceeded);
+
+        position.pool = pool.key();
+        position.user = user.key();
+        pool.record_deposit(position, amount)?;
+        let holding = position.tokens_mut(verdict);
+        *holding = holding
+            .checked_add(tokens)
+            .ok_or(PredictionError::Overflow)?;
+
+        emit!(OutcomeTradedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            verdict,
+            amount,
+            tokens,
+            fee,
+            is_buy: true,
+        });
+
+        Ok(())
+    }
+
+    pub fn sell_outcome(
+        ctx: Context<PoolTrade>,
+        verdict: bool,
+        return_amount: u64,
+        max_tokens: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(return_amount > 0, PredictionError::InvalidAmount);
+
+        // Gross up so the seller nets `return_amount` after the fee
+        let gross = div_ceil(
+            (return_amount as u128)
+                .checked_mul(BPS_DENOMINATOR as u128)
+                .ok_or(PredictionError::Overflow)?,
+            (BPS_DENOMINATOR - pool.fee_bps) as u128,
+        )?;
+        let fee = gross - return_amount;
+
+        let tokens = pool.sell(verdict, gross)?;
+        require!(tokens <= max_tokens, PredictionError::SlippageExceeded);
+
+        let holding = position.tokens_mut(verdict);
+        require!(*holding >= tokens, PredictionError::InsufficientShares);
+        *holding -= tokens;
+
+        pool.collect_fee(fee)?;
+        pool.record_withdrawal(position, return_amount)?;
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            return_amount,
+        )?;
+
+        emit!(OutcomeTradedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            verdict,
+            amount: return_amount,
+            tokens,
+            fee,
+            is_buy: false,
+        });
+
+        Ok(())
+    }
+
+    /// Post-resolution exit for LPs and traders: LP shares are converted to
+    /// their slice of the reserves and every winning token redeems 1:1. In a
+    /// voided market every position gets back its cost basis, what it paid
+    /// in less what it already took out, fees included.
+    pub fn redeem_pool_position(ctx: Context<PoolTrade>) -> Result<()> {
+        let market_state = &ctx.accounts.market_state;
+        let prediction = &ctx.accounts.prediction;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        require!(
+            prediction.state == PredictionState::Resolved
+                || prediction.state == PredictionState::Cancelled,
+            PredictionError::PredictionNotResolved
+        );
+
+        let shares = position.lp_shares;
+        let (winnings, fees) = if prediction.state == PredictionState::Cancelled {
+            // Trading fees go back to the traders with their stakes
+            let refund = pool.refund_for(position)?;
+            position.lp_shares = 0;
+            position.fee_debt = 0;
+            position.cost = 0;
+            (refund, 0)
+        } else {
+            // Refunds stay open under the global pause, payouts do not
+            market_state.require_not_paused()?;
+            let fees = pool.settle_fees(position)?;
+            if shares > 0 {
+                let total_shares = pool.total_shares as u128;
+                let yes_removed = mul_div(pool.yes_reserve, shares, total_shares)?;
+                let no_removed = mul_div(pool.no_reserve, shares, total_shares)?;
+                pool.yes_reserve -= yes_removed;
+                pool.no_reserve -= no_removed;
+                pool.total_shares -= shares;
+                position.yes_tokens = position
+                    .yes_tokens
+                    .checked_add(yes_removed)
+                    .ok_or(PredictionError::Overflow)?;
+                position.no_tokens = position
+                    .no_tokens
+                    .checked_add(no_removed)
+                    .ok_or(PredictionError::Overflow)?;
+                position.lp_shares = 0;
+                position.fee_debt = 0;
+            }
+            let winnings = match prediction.winning_outcome()? {
+                0 => position.yes_tokens,
+                _ => position.no_tokens,
+            };
+            (winnings, fees)
+        };
+        position.yes_tokens = 0;
+        position.no_tokens = 0;
+
+        let amount = winnings
+            .checked_add(fees)
+            .ok_or(PredictionError::Overflow)?;
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+
+        emit!(PoolRedeemedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            shares,
+            winnings,
+            fees,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_order_book(ctx: Context<InitializeOrderBook>) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+        let order_book = &mut ctx.accounts.order_book;
+
+        prediction.require_active()?;
+        require!(
+            prediction.prediction_type == PREDICTION_TYPE_BINARY,
+            PredictionError::InvalidPredictionType
+        );
+        require!(
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL && prediction.total_amount == 0,
+            PredictionError::InvalidMarketMode
+        );
+        require!(
+            prediction.is_native(),
+            PredictionError::UnsupportedCollateral
+        );
+
+        prediction.market_mode = MARKET_MODE_ORDER_BOOK;
+
+        order_book.prediction = prediction.key();
+        order_book.next_order_id = 0;
+        order_book.orders = Vec::new();
+        order_book.bump = ctx.bumps.order_book;
+
+        Ok(())
+    }
+
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        verdict: bool,
+        price_bps: u16,
+        quantity: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let order_book = &mut ctx.accounts.order_book;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(
+            price_bps > 0 && price_bps < BPS_DENOMINATOR,
+            PredictionError::InvalidPrice
+        );
+        // Whole multiples of the price denominator keep every fill exact.
+        // `is_multiple_of` is newer than the platform-tools rustc.
+        #[allow(clippy::manual_is_multiple_of)]
+        let whole_lots = quantity % BPS_DENOMINATOR as u64 == 0;
+        require!(quantity > 0 && whole_lots, PredictionError::InvalidAmount);
+        require!(
+            order_book.orders.len() < MAX_OPEN_ORDERS,
+            PredictionError::OrderBookFull
+        );
+
+        let escrow = order_cost(quantity, price_bps)?;
+        // A floor on escrow keeps the bounded book from being filled for dust
+        require!(escrow >= MIN_ORDER_ESCROW, PredictionError::OrderTooSmall);
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: user.to_account_info(),
+                to: order_book.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, escrow)?;
+
+        let order_id = order_book.next_order_id;
+        order_book.next_order_id = order_book
+            .next_order_id
+            .checked_add(1)
+            .ok_or(PredictionError::Overflow)?;
+        order_book.orders.push(Order {
+            id: order_id,
+            owner: user.key(),
+            verdict,
+            price_bps,
+            quantity,
+        });
+
+        position.prediction = prediction.key();
+        position.user = user.key();
+
+        emit!(OrderPlacedEvent {
+            prediction_id: prediction.id,
+            order_id,
+            owner: user.key(),
+            verdict,
+            price_bps,
+            quantity,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        ctx.accounts.prediction.require_active()?;
+
+        let order_book = &mut ctx.accounts.order_book;
+        let user = &ctx.accounts.user;
+
+        let index = order_book.find_order(order_id)?;
+        require!(
+            order_book.orders[index].owner == user.key(),
+            PredictionError::NotAuthorized
+        );
+        let order = order_book.orders.remove(index);
+
+        let refund = order_cost(order.quantity, order.price_bps)?;
+        transfer_lamports(
+            &order_book.to_account_info(),
+            &user.to_account_info(),
+            refund,
+        )?;
+
+        emit!(OrderCancelledEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            order_id,
+            owner: user.key(),
+            refund,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the escrow of an order left open when its market settled
+    /// outside `resolve_prediction`, which refunds the book itself. Anyone
+    /// can crank it; the lamports always go to the order's owner.
+    pub fn reclaim_order(ctx: Context<ReclaimOrder>, order_id: u64) -> Result<()> {
+        let prediction = &ctx.accounts.prediction;
+        let order_book = &mut ctx.accounts.order_book;
+
+        require!(
+            prediction.state == PredictionState::Resolved
+                || prediction.state == PredictionState::Cancelled,
+            PredictionError::PredictionNotResolved
+        );
+        let index = order_book.find_order(order_id)?;
+        require!(
+            ctx.accounts.owner.key() == order_book.orders[index].owner,
+            PredictionError::InvalidOrderAccount
+        );
+        let order = order_book.orders.remove(index);
+
+        let refund = order_cost(order.quantity, order.price_bps)?;
+        transfer_lamports(
+            &order_book.to_account_info(),
+            &ctx.accounts.owner.to_account_info(),
+            refund,
+        )?;
+
+        emit!(OrderCancelledEvent {
+            prediction_id: prediction.id,
+            order_id,
+            owner: order.owner,
+            refund,
+        });
+
+        Ok(())
+    }
+
+    /// Crosses a YES order against a NO order whose prices sum to at least
+    /// 100%. The fill executes at the older (maker) order's price and any
+    /// escrow above that price is refunded to the taker.
+    pub fn match_orders(
+        ctx: Context<MatchOrders>,
+        yes_order_id: u64,
+        no_order_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let order_book = &mut ctx.accounts.order_book;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+
+        let yes_index = order_book.find_order(yes_order_id)?;
+        let no_index = order_book.find_order(no_order_id)?;
+        let yes_order = order_book.orders[yes_index].clone();
+        let no_order = order_book.orders[no_index].clone();
+
+        require!(
+            yes_order.verdict && !no_order.verdict,
+            PredictionError::OrdersDoNotCross
+        );
+        require!(
+            yes_order.price_bps + no_order.price_bps >= BPS_DENOMINATOR,
+            PredictionError::OrdersDoNotCross
+        );
+        require!(
+            yes_order.owner != no_order.owner,
+            PredictionError::OrdersDoNotCross
+        );
+        require!(
+            ctx.accounts.yes_owner.key() == yes_order.owner
+                && ctx.accounts.yes_position.user == yes_order.owner,
+            PredictionError::InvalidOrderAccount
+        );
+        require!(
+            ctx.accounts.no_owner.key() == no_order.owner
+                && ctx.accounts.no_position.user == no_order.owner,
+            PredictionError::InvalidOrderAccount
+        );
+
+        let fill = yes_order.quantity.min(no_order.quantity);
+        let yes_price = if yes_order.id < no_order.id {
+            yes_order.price_bps
+        } else {
+            BPS_DENOMINATOR - no_order.price_bps
+        };
+        let yes_cost = order_cost(fill, yes_price)?;
+        let no_cost = fill - yes_cost;
+        let yes_refund = order_cost(fill, yes_order.price_bps)? - yes_cost;
+        let no_refund = order_cost(fill, no_order.price_bps)? - no_cost;
+
+        order_book.orders[yes_index].quantity -= fill;
+        order_book.orders[no_index].quantity -= fill;
+        order_book.orders.retain(|order| order.quantity > 0);
+
+        // Matched collateral backs one YES and one NO contract per lamport
+        let order_book_info = order_book.to_account_info();
+        transfer_lamports(
+            &order_book_info,
+            &ctx.accounts.vault.to_account_info(),
+            fill,
+        )?;
+        transfer_lamports(
+            &order_book_info,
+            &ctx.accounts.yes_owner.to_account_info(),
+            yes_refund,
+        )?;
+        transfer_lamports(
+            &order_book_info,
+            &ctx.accounts.no_owner.to_account_info(),
+            no_refund,
+        )?;
+
+        let yes_position = &mut ctx.accounts.yes_position;
+        yes_position.yes_contracts = yes_position
+            .yes_contracts
+            .checked_add(fill)
+            .ok_or(PredictionError::Overflow)?;
+        yes_position.cost = yes_position
+            .cost
+            .checked_add(yes_cost)
+            .ok_or(PredictionError::Overflow)?;
+        let no_position = &mut ctx.accounts.no_position;
+        no_position.no_contracts = no_position
+            .no_contracts
+            .checked_add(fill)
+            .ok_or(PredictionError::Overflow)?;
+        no_position.cost = no_position
+            .cost
+            .checked_add(no_cost)
+            .ok_or(PredictionError::Overflow)?;
+
+        emit!(OrdersMatchedEvent {
+            prediction_id: prediction.id,
+            yes_order_id,
+            no_order_id,
+            quantity: fill,
+            yes_price_bps: yes_price,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out matched contracts: every winning contract redeems for one
+    /// lamport, and a voided market returns what the position paid in fills.
+    pub fn redeem_order_position(ctx: Context<RedeemOrderPosition>) -> Result<()> {
+        let prediction = &ctx.accounts.prediction;
+        let position = &mut ctx.accounts.position;
+        let user = &ctx.accounts.user;
+
+        let amount = match prediction.state {
+            PredictionState::Resolved => {
+                // Refunds stay open under the global pause, payouts do not
+                ctx.accounts.market_state.require_not_paused()?;
+                match prediction.winning_outcome()? {
+                    0 => position.yes_contracts,
+                    _ => position.no_contracts,
+                }
+            }
+            PredictionState::Cancelled => position.cost,
+            _ => return err!(PredictionError::PredictionNotResolved),
+        };
+        require!(amount > 0, PredictionError::UserNotWinner);
+        position.yes_contracts = 0;
+        position.no_contracts = 0;
+        position.cost = 0;
+
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            user.to_account_info(),
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+
+        emit!(RewardClaimedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause_prediction(ctx: Context<UpdatePrediction>) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+        prediction.require_active()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        prediction.state = PredictionState::Paused;
+        prediction.paused_at = now;
+
+        emit!(PredictionPausedEvent {
+            prediction_id: prediction.id,
+            paused_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Reopens a paused market. With `extend_end_time` the deadlines move
+    /// out by however long the market was paused.
+    pub fn resume_prediction(ctx: Context<UpdatePrediction>, extend_end_time: bool) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+        require!(
+            prediction.state == PredictionState::Paused,
+            PredictionError::PredictionNotPaused
+        );
+
+        let paused_duration = Clock::get()?
+            .unix_timestamp
+            .saturating_sub(prediction.paused_at);
+        if extend_end_time {
+            prediction.end_time = prediction
+                .end_time
+                .checked_add(paused_duration)
+                .ok_or(PredictionError::Overflow)?;
+            prediction.cancel_deadline = prediction
+                .cancel_deadline
+                .checked_add(paused_duration)
+                .ok_or(PredictionError::Overflow)?;
+        }
+        prediction.state = PredictionState::Active;
+        prediction.paused_at = 0;
+
+        emit!(PredictionResumedEvent {
+            prediction_id: prediction.id,
+            paused_duration,
+            end_time: prediction.end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Binds a price feed to a binary or scalar market before any stake is
+    /// placed. Binary markets resolve `True` when the price compares to
+    /// `threshold` under `operator`; scalar markets take the price itself.
+    /// Only resolvers can bind, and only feeds owned by the oracle program.
+    pub fn set_price_feed(
+        ctx: Context<ConfigurePriceFeed>,
+        operator: u8,
+        threshold: i64,
+        exponent: i32,
+        max_staleness: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        prediction.require_active()?;
+        require!(
+            prediction.total_amount == 0,
+            PredictionError::MarketAlreadyFunded
+        );
+        require!(
+            prediction.prediction_type == PREDICTION_TYPE_BINARY
+                || prediction.prediction_type == PREDICTION_TYPE_SCALAR,
+            PredictionError::InvalidPredictionType
+        );
+        require!(
+            operator == FEED_OPERATOR_GREATER_THAN || operator == FEED_OPERATOR_LESS_THAN,
+            PredictionError::InvalidFeedOperator
+        );
+        require!(max_staleness > 0, PredictionError::InvalidDuration);
+        require!(
+            max_confidence_bps <= BPS_DENOMINATOR,
+            PredictionError::InvalidFee
+        );
+        // Reject accounts that are not in the feed layout up front
+        price_feed::load(&ctx.accounts.price_feed.try_borrow_data()?)?;
+
+        prediction.price_feed = ctx.accounts.price_feed.key();
+        prediction.feed_operator = operator;
+        prediction.feed_threshold = threshold;
+        prediction.feed_exponent = exponent;
+        prediction.feed_max_staleness = max_staleness;
+        prediction.feed_max_confidence_bps = max_confidence_bps;
+
+        emit!(PriceFeedConfiguredEvent {
+            prediction_id: prediction.id,
+            price_feed: prediction.price_feed,
+            operator,
+            threshold,
+            exponent,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless settlement from the market's price feed once it has
+    /// ended. The price must be published after `end_time`, be fresh and
+    /// have a tight enough confidence interval.
+    pub fn resolve_from_feed(ctx: Context<ResolveFromFeed>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &mut ctx.accounts.prediction;
+
+        prediction.require_active()?;
+        require!(!prediction.resolver_quorum, PredictionError::QuorumRequired);
+        require!(
+            prediction.price_feed != Pubkey::default(),
+            PredictionError::InvalidPriceFeed
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= prediction.end_time,
+            PredictionError::PredictionNotEnded
+        );
+
+        let feed = price_feed::load(&ctx.accounts.price_feed.try_borrow_data()?)?;
+        require!(
+            feed.exponent == prediction.feed_exponent,
+            PredictionError::FeedExponentMismatch
+        );
+        require!(
+            feed.publish_time >= prediction.end_time
+                && now.saturating_sub(feed.publish_time) <= prediction.feed_max_staleness,
+            PredictionError::FeedStale
+        );
+        let max_confidence = (feed.price.unsigned_abs() as u128)
+            .checked_mul(prediction.feed_max_confidence_bps as u128)
+            .ok_or(PredictionError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        require!(
+            feed.confidence as u128 <= max_confidence,
+            PredictionError::FeedConfidenceTooWide
+        );
+
+        let result = if prediction.prediction_type == PREDICTION_TYPE_SCALAR {
+            PredictionResult::Scalar(feed.price)
+        } else {
+            let above = feed.price > prediction.feed_threshold;
+            let below = feed.price < prediction.feed_threshold;
+            let hit = if prediction.feed_operator == FEED_OPERATOR_GREATER_THAN {
+                above
+            } else {
+                below
+            };
+            if hit {
+                PredictionResult::True
+            } else {
+                PredictionResult::False
+            }
+        };
+        prediction.settle(result)?;
+
+        emit!(FeedResolvedEvent {
+            prediction_id: prediction.id,
+            price: feed.price,
+            confidence: feed.confidence,
+            publish_time: feed.publish_time,
+        });
+        emit!(PredictionResolvedEvent {
+            prediction_id: prediction.id,
+            result,
+        });
+
+        Ok(())
+    }
+
+    /// Settles a market from the registered oracle's signature over
+    /// `attestation::message`. The ed25519 verification must be the
+    /// instruction immediately before this one; anyone may relay it.
+    pub fn resolve_with_attestation(
+        ctx: Context<ResolveWithAttestation>,
+        result: PredictionResult,
+        evidence_hash: [u8; 32],
+        timestamp: i64,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let oracle = ctx.accounts.market_state.oracle;
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(
+            oracle != Pubkey::default(),
+            PredictionError::InvalidAttestation
+        );
+        prediction.require_active()?;
+        require!(!prediction.resolver_quorum, PredictionError::QuorumRequired);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= prediction.end_time,
+            PredictionError::PredictionNotEnded
+        );
+        require!(
+            timestamp >= prediction.end_time && timestamp <= now,
+            PredictionError::InvalidAttestation
+        );
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+
+        let message = attestation::message(&prediction.key(), &result, &evidence_hash, timestamp)?;
+        attestation::verify(&ctx.accounts.instructions, &oracle, &message)?;
+
+        prediction.settle(result)?;
+
+        emit!(AttestationResolvedEvent {
+            prediction_id: prediction.id,
+            oracle,
+            result,
+            evidence_hash,
+            timestamp,
+        });
+        emit!(PredictionResolvedEvent {
+            prediction_id: prediction.id,
+            result,
+        });
+
+        Ok(())
+    }
+
+    /// Hands settlement to `threshold` of `resolvers`, who must all agree.
+    /// Only possible while the market is still being set up, before any
+    /// stake is placed and before it switches to another market mode, so
+    /// nobody's funds end up under resolvers they did not sign up for.
+    pub fn initialize_resolver_quorum(
+        ctx: Context<InitializeResolverQuorum>,
+        resolvers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp < prediction.end_time,
+            PredictionError::PredictionEnded
+        );
+        require!(
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL && prediction.total_amount == 0,
+            PredictionError::MarketAlreadyFunded
+        );
+        require!(
+            !resolvers.is_empty() && resolvers.len() <= MAX_RESOLVERS,
+            PredictionError::InvalidQuorum
+        );
+        require!(
+            threshold > 0 && threshold as usize <= resolvers.len(),
+            PredictionError::InvalidQuorum
+        );
+        for (index, resolver) in resolvers.iter().enumerate() {
+            require!(
+                !resolvers[..index].contains(resolver),
+                PredictionError::InvalidQuorum
+            );
+        }
+
+        prediction.resolver_quorum = true;
+
+        let quorum = &mut ctx.accounts.quorum;
+        quorum.prediction = prediction.key();
+        quorum.threshold = threshold;
+        quorum.resolvers = resolvers;
+        quorum.votes = Vec::new();
+        quorum.bump = ctx.bumps.quorum;
+
+        emit!(ResolverQuorumInitializedEvent {
+            prediction_id: prediction.id,
+            resolvers: quorum.resolvers.clone(),
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Records one resolver's vote. The market settles once `threshold`
+    /// matching votes are in; any disagreement marks it disputed for a
+    /// resolver to review through `resolve_prediction`.
+    pub fn submit_resolver_vote(
+        ctx: Context<SubmitResolverVote>,
+        result: PredictionResult,
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &mut ctx.accounts.prediction;
+        let quorum = &mut ctx.accounts.quorum;
+        let resolver = ctx.accounts.resolver.key();
+
+        prediction.require_active()?;
+        require!(
+            Clock::get()?.unix_timestamp >= prediction.end_time,
+            PredictionError::PredictionNotEnded
+        );
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+        require!(
+            quorum.resolvers.contains(&resolver),
+            PredictionError::NotAuthorized
+        );
+        require!(
+            quorum.votes.iter().all(|vote| vote.resolver != resolver),
+            PredictionError::AlreadyVoted
+        );
+
+        let conflicting = quorum
+            .votes
+            .iter()
+            .find(|vote| vote.result != result)
+            .map(|vote| vote.result);
+        quorum.votes.push(ResolverVote { resolver, result });
+
+        emit!(ResolverVoteSubmittedEvent {
+            prediction_id: prediction.id,
+            resolver,
+            result,
+        });
+
+        if let Some(conflicting_result) = conflicting {
+            prediction.state = PredictionState::Disputed;
+
+            emit!(QuorumDisputedEvent {
+                prediction_id: prediction.id,
+                resolver,
+                result,
+                conflicting_result,
+            });
+        } else if quorum.votes.len() >= quorum.threshold as usize {
+            prediction.settle(result)?;
+
+            emit!(PredictionResolvedEvent {
+                prediction_id: prediction.id,
+                result,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Settles a market by hand. On an order book market every open order
+    /// is cancelled and refunded in the same instruction, so the owners of
+    /// all resting orders must be passed as writable remaining accounts.
+    pub fn resolve_prediction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolvePrediction<'info>>,
+        result: PredictionResult,
+    ) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        // A split quorum comes here for manual review; otherwise only open
+        // markets qualify, since a proposal has to run its bonded course
+        if prediction.resolver_quorum {
+            require!(
+                prediction.state == PredictionState::Disputed,
+                PredictionError::QuorumRequired
+            );
+        } else {
+            require!(
+                prediction.state == PredictionState::Active
+                    || prediction.state == PredictionState::Paused,
+                PredictionError::PredictionAlreadyResolved
+            );
+        }
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+
+        prediction.settle(result)?;
+
+        if prediction.market_mode == MARKET_MODE_ORDER_BOOK {
+            let order_book = ctx
+                .accounts
+                .order_book
+                .as_mut()
+                .ok_or(PredictionError::MissingOrderBook)?;
+            refund_open_orders(prediction.id, order_book, ctx.remaining_accounts)?;
+        }
+
+        emit!(PredictionResolvedEvent {
+            prediction_id: prediction.id,
+            result,
+        });
+
+        Ok(())
+    }
+
+    /// Queues a winner's reward for a treasurer to pay through
+    /// `approve_claims`. Submitting marks the position claimed, so the same
+    /// reward cannot also be taken through `claim_reward`.
+    pub fn submit_claim(ctx: Context<SubmitClaim>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let claims = &mut ctx.accounts.claims;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+
+        require!(
+            prediction.state == PredictionState::Resolved,
+            PredictionError::PredictionNotResolved
+        );
+        require!(
+            prediction.rewards_distributed,
+            PredictionError::RewardsNotDistributed
+        );
+        // Claims are paid in lamports straight from the vault
+        require!(
+            prediction.is_native(),
+            PredictionError::UnsupportedCollateral
+        );
+        require!(
+            !user_prediction.reward_claimed,
+            PredictionError::RewardAlreadyClaimed
+        );
+
+        let is_winner = prediction.is_winner(user_prediction)?;
+        require!(is_winner, PredictionError::UserNotWinner);
+
+        require!(
+            claims.pending_claims.len() < MAX_CLAIMS,
+            PredictionError::ClaimsFull
+        );
+
+        let reward_amount = prediction.reward_for(user_prediction)?;
+        user_prediction.reward_claimed = true;
+
+        claims.pending_claims.push(Claim {
+            user: ctx.accounts.user.key(),
+            amount: reward_amount,
+            state: ClaimState::Pending,
+        });
+
+        emit!(ClaimSubmittedEvent {
+            prediction_id: prediction.id,
+            user: ctx.accounts.user.key(),
+            amount: reward_amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn distribute_rewards(ctx: Context<DistributeRewards>) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+        let market_state = &ctx.accounts.market_state;
+
+        // Ensure the prediction is resolved
+        require!(
+            prediction.state == PredictionState::Resolved,
+            PredictionError::PredictionNotResolved
+        );
+
+        // Ensure rewards haven't been distributed yet
+        require!(
+            !prediction.rewards_distributed,
+            PredictionError::RewardsAlreadyDistributed
+        );
+
+        // Pool and order book markets settle through their own redemptions
+        require!(
+            prediction.market_mode == MARKET_MODE_PARIMUTUEL
+                || prediction.market_mode == MARKET_MODE_LMSR,
+            PredictionError::InvalidMarketMode
+        );
+
+        // Nobody backed the winning side, so there is no one to pay: void the
+        // market and let every bettor take their stake back instead
+        if prediction.market_mode == MARKET_MODE_PARIMUTUEL
+            && prediction.prediction_type != PREDICTION_TYPE_SCALAR
+            && prediction.winning_amount()? == 0
+        {
+            prediction.state = PredictionState::Cancelled;
+
+            emit!(PredictionVoidedEvent {
+                prediction_id: prediction.id,
+                total_pool: prediction.total_amount,
+            });
+
+            return Ok(());
+        }
+
+        let total_pool = prediction.total_amount;
+        let (reward_pool, protocol_fee, creator_fee) = prediction.fee_split()?;
+        // The creator's cut stays in the vault until `claim_creator_fee`
+        prediction.creator_fee = creator_fee;
+
+        emit!(FeeSplitEvent {
+            prediction_id: prediction.id,
+            creator: prediction.creator,
+            protocol_fee,
+            creator_fee,
+        });
+
+        // Transfer the protocol's share of the fee
+        if prediction.is_native() {
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                ctx.accounts.fee_recipient.to_account_info(),
+                &ctx.accounts.system_program,
+                protocol_fee,
+            )?;
+        } else {
+            let (mint, token_vault, token_program) = token_vault_accounts(
+                prediction,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.token_program,
+            )?;
+            let fee_token_account = ctx
+                .accounts
+                .fee_token_account
+                .as_ref()
+                .ok_or(PredictionError::MissingTokenAccounts)?;
+            // The treasury cannot sign for tokens, so its treasurer holds them
+            let fee_owner = if market_state.fee_recipient == market_state.key() {
+                ctx.accounts.authority.key()
+            } else {
+                market_state.fee_recipient
+            };
+            require!(
+                fee_token_account.owner == fee_owner,
+                PredictionError::InvalidFeeRecipient
+            );
+            transfer_from_token_vault(
+                prediction,
+                token_program,
+                mint,
+                token_vault,
+                fee_token_account,
+                protocol_fee,
+            )?;
+        }
+
+        let reward_per_lamport = if prediction.market_mode == MARKET_MODE_LMSR {
+            // Every winning share redeems for one lamport
+            REWARD_PRECISION
+        } else if prediction.prediction_type == PREDICTION_TYPE_SCALAR {
+            // Scalar markets pay both sides linearly by where the value landed
+            let (long_reward_per_lamport, short_reward_per_lamport) =
+                prediction.scalar_reward_rates(reward_pool)?;
+            prediction.short_reward_per_lamport = short_reward_per_lamport;
+
+            emit!(ScalarRewardsDistributedEvent {
+                prediction_id: prediction.id,
+                value: prediction.scalar_value()?,
+                long_reward_per_lamport,
+                short_reward_per_lamport,
+            });
+
+            long_reward_per_lamport
+        } else {
+            // Calculate winning amount
+            let winning_amount = prediction.winning_amount()?;
+
+            // Calculate reward per lamport
+            mul_div(reward_pool, REWARD_PRECISION, winning_amount as u128)?
+        };
+
+        prediction.reward_per_lamport = reward_per_lamport;
+        prediction.rewards_distributed = true;
+
+        emit!(RewardsDistributedEvent {
+            prediction_id: prediction.id,
+            total_pool,
+            admin_fee: protocol_fee + creator_fee,
+            reward_pool,
+            reward_per_lamport,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+        let user = &ctx.accounts.user;
+
+        // Ensure the prediction is resolved and rewards are distributed
+        require!(
+            prediction.state == PredictionState::Resolved,
+            PredictionError::PredictionNotResolved
+        );
+        require!(
+            prediction.rewards_distributed,
+            PredictionError::RewardsNotDistributed
+        );
+
+        // Ensure the user hasn't claimed their reward yet
+        require!(
+            !user_prediction.reward_claimed,
+            PredictionError::RewardAlreadyClaimed
+        );
+
+        // Check if the user is a winner
+        let is_winner = prediction.is_winner(user_prediction)?;
+        require!(is_winner, PredictionError::UserNotWinner);
+
+        // Calculate the reward
+        let reward = prediction.reward_for(user_prediction)?;
+
+        // Transfer the reward
+        if prediction.is_native() {
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                user.to_account_info(),
+                &ctx.accounts.system_program,
+                reward,
+            )?;
+        } else {
+            let (mint, token_vault, token_program) = token_vault_accounts(
+                prediction,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.token_program,
+            )?;
+            let user_token_account = ctx
+                .accounts
+                .user_token_account
+                .as_ref()
+                .ok_or(PredictionError::MissingTokenAccounts)?;
+            transfer_from_token_vault(
+                prediction,
+                token_program,
+                mint,
+                token_vault,
+                user_token_account,
+                reward,
+            )?;
+        }
+
+        // Mark the reward as claimed
+        user_prediction.reward_claimed = true;
+
+        emit!(RewardClaimedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            amount: reward,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the caller's profile on this marketplace, starting at
+    /// `INITIAL_REPUTATION`. Only this marketplace's markets can score it.
+    pub fn register_user(ctx: Context<RegisterUser>, alias: String) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        require!(
+            alias.len() <= MAX_ALIAS_LENGTH,
+            PredictionError::AliasTooLong
+        );
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.user = ctx.accounts.user.key();
+        user_account.market = ctx.accounts.market_state.key();
+        user_account.alias = alias;
+        user_account.rank = 0;
+        user_account.reputation = INITIAL_REPUTATION;
+        user_account.total_predictions = 0;
+        user_account.scored_predictions = 0;
+        user_account.correct_predictions = 0;
+        user_account.rank_season = 0;
+        user_account.bump = ctx.bumps.user_account;
+
+        emit!(UserRegisteredEvent {
+            user: user_account.user,
+            alias: user_account.alias.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn update_alias(ctx: Context<UpdateAlias>, alias: String) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        require!(
+            alias.len() <= MAX_ALIAS_LENGTH,
+            PredictionError::AliasTooLong
+        );
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.alias = alias;
+
+        emit!(AliasUpdatedEvent {
+            user: user_account.user,
+            alias: user_account.alias.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_leaderboard(ctx: Context<InitializeLeaderboard>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.market = ctx.accounts.market_state.key();
+        leaderboard.season = 1;
+        leaderboard.season_started_at = now;
+        leaderboard.updated_at = now;
+        leaderboard.entries = Vec::new();
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        emit!(SeasonStartedEvent {
+            season: leaderboard.season,
+            started_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Clears the board and starts a new season. Ranks written during the
+    /// previous season stay on profiles, tagged with that season.
+    pub fn reset_season(ctx: Context<ResetSeason>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.season = leaderboard
+            .season
+            .checked_add(1)
+            .ok_or(PredictionError::Overflow)?;
+        leaderboard.season_started_at = now;
+        leaderboard.updated_at = now;
+        leaderboard.entries.clear();
+
+        emit!(SeasonStartedEvent {
+            season: leaderboard.season,
+            started_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Re-ranks the profiles passed as remaining accounts by reputation and
+    /// writes each one's rank back, 0 meaning off the board. Profiles with
+    /// fewer than `MIN_RANKED_PREDICTIONS` scored predictions stay off it, so
+    /// fresh or barely used profiles cannot crowd the board, and profiles
+    /// from other marketplaces are rejected. Anyone can crank it; entries
+    /// for profiles not passed keep their last reputation.
+    pub fn update_leaderboard<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateLeaderboard<'info>>,
+    ) -> Result<()> {
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            PredictionError::InvalidUserAccount
+        );
+
+        let mut candidates = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts.iter() {
+            require!(info.is_writable, PredictionError::InvalidUserAccount);
+            let user_account = Account::<UserAccount>::try_from(info)?;
+            // Only profiles this marketplace scores may rank on its board
+            require!(
+                user_account.market == leaderboard.market,
+                PredictionError::InvalidUserAccount
+            );
+            let expected = Pubkey::create_program_address(
+                &[
+                    b"user",
+                    leaderboard.market.as_ref(),
+                    user_account.user.as_ref(),
+                    &[user_account.bump],
+                ],
+                ctx.program_id,
+            )
+            .map_err(|_| PredictionError::InvalidUserAccount)?;
+            require!(info.key() == expected, PredictionError::InvalidUserAccount);
+            if user_account.scored_predictions >= MIN_RANKED_PREDICTIONS {
+                leaderboard.insert(user_account.user, user_account.reputation);
+            } else {
+                leaderboard.remove(&user_account.user);
+            }
+            candidates.push(user_account);
+        }
+
+        for mut user_account in candidates {
+            user_account.rank = leaderboard.rank_of(&user_account.user);
+            user_account.rank_season = leaderboard.season;
+            user_account.exit(ctx.program_id)?;
+        }
+        leaderboard.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(LeaderboardUpdatedEvent {
+            season: leaderboard.season,
+            entries: leaderboard.entries.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Scores a position on a settled market against the bettor's profile.
+    /// Anyone can crank it, once per position, after rewards are distributed.
+    /// Every staked position counts as a scored prediction; it is accurate
+    /// only if it pays out more than it cost, so hedging every outcome never is.
+    /// Accurate positions gain reputation and losing ones cost some. Voided
+    /// markets and fully cancelled positions leave the profile unchanged.
+    pub fn update_reputation(ctx: Context<UpdateReputation>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let prediction = &ctx.accounts.prediction;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+
+        require!(
+            prediction.state == PredictionState::Resolved
+                || prediction.state == PredictionState::Cancelled,
+            PredictionError::PredictionNotResolved
+        );
+        require!(
+            prediction.state == PredictionState::Cancelled || prediction.rewards_distributed,
+            PredictionError::RewardsNotDistributed
+        );
+        require!(!user_prediction.scored, PredictionError::AlreadyScored);
+        user_prediction.scored = true;
+
+        let user_account = &mut ctx.accounts.user_account;
+        let stake = user_prediction.amount;
+        let scored = prediction.state == PredictionState::Resolved && stake > 0;
+        let payout = if scored && prediction.is_winner(user_prediction)? {
+            prediction.reward_for(user_prediction)?
+        } else {
+            0
+        };
+        let correct = scored && payout > stake;
+        if scored {
+            user_account.scored_predictions = user_account
+                .scored_predictions
+                .checked_add(1)
+                .ok_or(PredictionError::Overflow)?;
+        }
+        if correct {
+            user_account.correct_predictions = user_account
+                .correct_predictions
+                .checked_add(1)
+                .ok_or(PredictionError::Overflow)?;
+            user_account.reputation = user_account
+                .reputation
+                .checked_add(REPUTATION_GAIN)
+                .ok_or(PredictionError::Overflow)?;
+        } else if scored && payout < stake {
+            user_account.reputation = user_account.reputation.saturating_sub(REPUTATION_LOSS);
+        }
+
+        emit!(ReputationUpdatedEvent {
+            user: user_account.user,
+            prediction_id: prediction.id,
+            correct,
+            reputation: user_account.reputation,
+            correct_predictions: user_account.correct_predictions,
+            total_predictions: user_account.total_predictions,
+        });
+
+        Ok(())
+    }
+
+    /// Refunds a position once the market has been voided: a parimutuel
+    /// bettor's net stake plus a pro-rata share of cancellation penalties, or
+    /// an LMSR trader's net spend, scaled down if the book cannot cover it.
+    pub fn claim_refund(ctx: Context<ClaimReward>) -> Result<()> {
+        let prediction = &ctx.accounts.prediction;
+        let user_prediction = &mut ctx.accounts.user_prediction;
+        let user = &ctx.accounts.user;
+
+        require!(
+            prediction.state == PredictionState::Cancelled,
+            PredictionError::PredictionNotCancelled
+        );
+        require!(
+            !user_prediction.reward_claimed,
+            PredictionError::RewardAlreadyClaimed
+        );
+        let refund = prediction.refund_for(user_prediction)?;
+        require!(refund > 0, PredictionError::NothingToRefund);
+
+        if prediction.is_native() {
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                user.to_account_info(),
+                &ctx.accounts.system_program,
+                refund,
+            )?;
+        } else {
+            let (mint, token_vault, token_program) = token_vault_accounts(
+                prediction,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.token_program,
+            )?;
+            let user_token_account = ctx
+                .accounts
+                .user_token_account
+                .as_ref()
+                .ok_or(PredictionError::MissingTokenAccounts)?;
+            transfer_from_token_vault(
+                prediction,
+                token_program,
+                mint,
+                token_vault,
+                user_token_account,
+                refund,
+            )?;
+        }
+
+        user_prediction.reward_claimed = true;
+
+        emit!(RefundClaimedEvent {
+            prediction_id: prediction.id,
+            user: user.key(),
+            amount: refund,
+        });
+
+        Ok(())
+    }
+
+    /// Releases the creator's bond once the market has settled. The bond goes
+    /// back to the creator unless the market was voided as `Invalid`, in which
+    /// case it is slashed to the treasury.
+    pub fn settle_creator_bond(ctx: Context<SettleCreatorBond>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(
+            prediction.state == PredictionState::Resolved
+                || prediction.state == PredictionState::Cancelled,
+            PredictionError::PredictionNotResolved
+        );
+        let bond = prediction.creator_bond;
+        require!(bond > 0, PredictionError::NoCreatorBond);
+        prediction.creator_bond = 0;
+
+        let slashed = prediction.result == PredictionResult::Invalid;
+        let recipient = if slashed {
+            ctx.accounts.market_state.to_account_info()
+        } else {
+            ctx.accounts.creator.to_account_info()
+        };
+        transfer_from_vault(
+            &ctx.accounts.prediction,
+            &ctx.accounts.vault,
+            recipient,
+            &ctx.accounts.system_program,
+            bond,
+        )?;
+
+        emit!(CreatorBondSettledEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            creator: ctx.accounts.creator.key(),
+            amount: bond,
+            slashed,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the creator the share of the fee set aside by `distribute_rewards`.
+    pub fn claim_creator_fee(ctx: Context<ClaimCreatorFee>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let amount = ctx.accounts.prediction.take_creator_fee()?;
+
+        let prediction = &ctx.accounts.prediction;
+        if prediction.is_native() {
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                ctx.accounts.creator.to_account_info(),
+                &ctx.accounts.system_program,
+                amount,
+            )?;
+        } else {
+            let (mint, token_vault, token_program) = token_vault_accounts(
+                prediction,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.token_program,
+            )?;
+            let creator_token_account = ctx
+                .accounts
+                .creator_token_account
+                .as_ref()
+                .ok_or(PredictionError::MissingTokenAccounts)?;
+            transfer_from_token_vault(
+                prediction,
+                token_program,
+                mint,
+                token_vault,
+                creator_token_account,
+                amount,
+            )?;
+        }
+
+        emit!(CreatorFeeClaimedEvent {
+            prediction_id: prediction.id,
+            creator: ctx.accounts.creator.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Proposes an outcome once the market has ended. Anyone may propose by
+    /// posting `market_state.proposal_bond`, which is held in the vault.
+    pub fn propose_result(ctx: Context<ProposeResult>, result: PredictionResult) -> Result<()> {
+        let market_state = &ctx.accounts.market_state;
+        market_state.require_not_paused()?;
+        let prediction = &ctx.accounts.prediction;
+
+        prediction.require_active()?;
+        require!(!prediction.resolver_quorum, PredictionError::QuorumRequired);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= prediction.end_time,
+            PredictionError::PredictionNotEnded
+        );
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+
+        let bond = market_state.proposal_bond;
+        let challenge_deadline = now
+            .checked_add(market_state.challenge_window)
+            .ok_or(PredictionError::Overflow)?;
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.proposer.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            bond,
+        )?;
+
+        let prediction = &mut ctx.accounts.prediction;
+        prediction.state = PredictionState::Proposed;
+        prediction.proposed_result = result;
+        prediction.proposer = ctx.accounts.proposer.key();
+        prediction.resolution_bond = bond;
+        prediction.challenge_deadline = challenge_deadline;
+
+        emit!(ResultProposedEvent {
+            prediction_id: prediction.id,
+            proposer: prediction.proposer,
+            result,
+            bond,
+            challenge_deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Challenges a pending proposal with a matching bond and escalates the
+    /// market to the resolver role for arbitration.
+    pub fn dispute_result(ctx: Context<DisputeResult>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let prediction = &ctx.accounts.prediction;
+
+        require!(
+            prediction.state == PredictionState::Proposed,
+            PredictionError::PredictionNotProposed
+        );
+        require!(
+            Clock::get()?.unix_timestamp < prediction.challenge_deadline,
+            PredictionError::ChallengeWindowClosed
+        );
+
+        let bond = prediction.resolution_bond;
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.disputer.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            bond,
+        )?;
+
+        let prediction = &mut ctx.accounts.prediction;
+        prediction.state = PredictionState::Disputed;
+        prediction.disputer = ctx.accounts.disputer.key();
+
+        emit!(ResultDisputedEvent {
+            prediction_id: prediction.id,
+            disputer: prediction.disputer,
+            bond,
+        });
+
+        Ok(())
+    }
+
+    /// Settles an undisputed proposal after the challenge window. The
+    /// proposer gets the bond back plus the treasury reward, if funded.
+    pub fn finalize_result(ctx: Context<FinalizeResult>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(
+            prediction.state == PredictionState::Proposed,
+            PredictionError::PredictionNotProposed
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= prediction.challenge_deadline,
+            PredictionError::ChallengeWindowOpen
+        );
+
+        let result = prediction.proposed_result;
+        prediction.settle(result)?;
+
+        let treasury = ctx.accounts.market_state.to_account_info();
+        let available = treasury
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(MarketState::LEN));
+        let reward = ctx.accounts.market_state.proposer_reward.min(available);
+        transfer_lamports(&treasury, &ctx.accounts.proposer.to_account_info(), reward)?;
+
+        let prediction = &ctx.accounts.prediction;
+        transfer_from_vault(
+            prediction,
+            &ctx.accounts.vault,
+            ctx.accounts.proposer.to_account_info(),
+            &ctx.accounts.system_program,
+            prediction.resolution_bond,
+        )?;
+
+        emit!(ResultFinalizedEvent {
+            prediction_id: prediction.id,
+            result,
+            proposer: prediction.proposer,
+            reward,
+        });
+
+        Ok(())
+    }
+
+    /// Decides a disputed market. Whichever of the proposer and disputer
+    /// turns out right collects both bonds.
+    pub fn arbitrate(ctx: Context<Arbitrate>, result: PredictionResult) -> Result<()> {
+        let prediction = &mut ctx.accounts.prediction;
+
+        require!(
+            prediction.state == PredictionState::Disputed,
+            PredictionError::PredictionNotDisputed
+        );
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+
+        prediction.settle(result)?;
+
+        let winner = if result == prediction.proposed_result {
+            ctx.accounts.proposer.to_account_info()
+        } else {
+            ctx.accounts.disputer.to_account_info()
+        };
+        let amount = prediction
+            .resolution_bond
+            .checked_mul(2)
+            .ok_or(PredictionError::Overflow)?;
+        transfer_from_vault(
+            &ctx.accounts.prediction,
+            &ctx.accounts.vault,
+            winner.clone(),
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+
+        emit!(DisputeArbitratedEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            result,
+            winner: winner.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Adds stake to the caller's juror entry, registering them on first use.
+    /// The lamports are held by the market's juror pool.
+    pub fn register_juror(ctx: Context<RegisterJuror>, amount: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        require!(amount > 0, PredictionError::InvalidAmount);
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.juror.to_account_info(),
+                    to: ctx.accounts.juror_pool.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let juror_pool = &mut ctx.accounts.juror_pool;
+        if juror_pool.market == Pubkey::default() {
+            juror_pool.market = ctx.accounts.market_state.key();
+            juror_pool.bump = ctx.bumps.juror_pool;
+        }
+
+        let juror = ctx.accounts.juror.key();
+        let stake = match juror_pool.find_juror(&juror) {
+            Some(index) => {
+                let entry = &mut juror_pool.jurors[index];
+                entry.stake = entry
+                    .stake
+                    .checked_add(amount)
+                    .ok_or(PredictionError::Overflow)?;
+                entry.stake
+            }
+            None => {
+                require!(
+                    juror_pool.jurors.len() < MAX_JURORS,
+                    PredictionError::JurorPoolFull
+                );
+                juror_pool.jurors.push(JurorEntry {
+                    authority: juror,
+                    stake: amount,
+                    locked: 0,
+                });
+                amount
+            }
+        };
+
+        emit!(JurorStakedEvent {
+            juror,
+            amount,
+            stake,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws stake that is not locked in an open dispute. A juror whose
+    /// stake reaches zero leaves the pool.
+    pub fn withdraw_juror_stake(ctx: Context<WithdrawJurorStake>, amount: u64) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        require!(amount > 0, PredictionError::InvalidAmount);
+
+        let juror = ctx.accounts.juror.key();
+        let juror_pool = &mut ctx.accounts.juror_pool;
+        let index = juror_pool
+            .find_juror(&juror)
+            .ok_or(PredictionError::NotJuror)?;
+        let entry = &mut juror_pool.jurors[index];
+        require!(
+            entry.stake - entry.locked >= amount,
+            PredictionError::InsufficientStake
+        );
+        entry.stake -= amount;
+        let stake = entry.stake;
+        if stake == 0 {
+            juror_pool.jurors.swap_remove(index);
+        }
+
+        transfer_lamports(
+            &ctx.accounts.juror_pool.to_account_info(),
+            &ctx.accounts.juror.to_account_info(),
+            amount,
+        )?;
+
+        emit!(JurorWithdrawnEvent {
+            juror,
+            amount,
+            stake,
+        });
+
+        Ok(())
+    }
+
+    /// Takes a disputed market to the juror court. The opener posts
+    /// `market_state.court_bond` and commits to the hash of a seed now, to be
+    /// revealed in `draw_panel` once the draw slot has passed.
+    pub fn open_dispute(ctx: Context<OpenDispute>, seed_commitment: [u8; 32]) -> Result<()> {
+        let market_state = &ctx.accounts.market_state;
+        market_state.require_not_paused()?;
+        let prediction = &ctx.accounts.prediction;
+
+        require!(
+            prediction.state == PredictionState::Disputed,
+            PredictionError::PredictionNotDisputed
+        );
+
+        let clock = Clock::get()?;
+        let draw_deadline = clock
+            .unix_timestamp
+            .checked_add(market_state.court_period)
+            .ok_or(PredictionError::Overflow)?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.prediction = prediction.key();
+        dispute.opener = ctx.accounts.opener.key();
+        dispute.seed_commitment = seed_commitment;
+        dispute.seed_slot = clock
+            .slot
+            .checked_add(court::DRAW_DELAY_SLOTS)
+            .ok_or(PredictionError::Overflow)?;
+        dispute.phase = DisputePhase::Drawing;
+        dispute.commit_deadline = draw_deadline;
+        dispute.reveal_deadline = draw_deadline;
+        dispute.juror_stake = 0;
+        dispute.bond = market_state.court_bond;
+        dispute.panel = Vec::new();
+        dispute.bump = ctx.bumps.dispute;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.opener.to_account_info(),
+                to: dispute.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, dispute.bond)?;
+
+        emit!(DisputeOpenedEvent {
+            prediction_id: prediction.id,
+            opener: dispute.opener,
+            seed_slot: dispute.seed_slot,
+            draw_deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Reveals the opener's seed and draws the panel from jurors with enough
+    /// free stake, locking `market_state.juror_stake` from each of them. The
+    /// seed is mixed with the hash of the draw slot fixed at open time, which
+    /// SlotHashes keeps for about `court::SLOT_HASHES_DEPTH` slots. The
+    /// proposer and disputer are never drawn.
+    pub fn draw_panel(ctx: Context<DrawPanel>, seed: [u8; 32]) -> Result<()> {
+        let market_state = &ctx.accounts.market_state;
+        market_state.require_not_paused()?;
+        let prediction = &ctx.accounts.prediction;
+        let dispute = &mut ctx.accounts.dispute;
+
+        require!(
+            dispute.phase == DisputePhase::Drawing,
+            PredictionError::InvalidCourtPhase
+        );
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp < dispute.commit_deadline,
+            PredictionError::InvalidCourtPhase
+        );
+        require!(
+            court::seed_commitment(&seed) == dispute.seed_commitment,
+            PredictionError::InvalidSeed
+        );
+
+        let slot_hash = court::slot_hash_at(
+            &ctx.accounts.slot_hashes.try_borrow_data()?,
+            dispute.seed_slot,
+        )?;
+        let mixed = anchor_lang::solana_program::hash::hashv(&[
+            &seed,
+            &slot_hash,
+            prediction.key().as_ref(),
+        ])
+        .to_bytes();
+
+        let stake = market_state.juror_stake;
+        let juror_pool = &mut ctx.accounts.juror_pool;
+        let candidates = juror_pool
+            .jurors
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                entry.stake - entry.locked >= stake
+                    && entry.authority != prediction.proposer
+                    && entry.authority != prediction.disputer
+            })
+            .map(|(index, _)| index)
+            .collect();
+        let picks = court::draw(&mixed, candidates, market_state.panel_size as usize)?;
+
+        let mut jurors = Vec::with_capacity(picks.len());
+        for index in picks {
+            let entry = &mut juror_pool.jurors[index];
+            entry.locked += stake;
+            jurors.push(entry.authority);
+            dispute.panel.push(JurorVote {
+                juror: entry.authority,
+                commitment: [0; 32],
+                committed: false,
+                revealed: false,
+                result: PredictionResult::Undefined,
+            });
+        }
+
+        dispute.phase = DisputePhase::Voting;
+        dispute.juror_stake = stake;
+        dispute.commit_deadline = clock
+            .unix_timestamp
+            .checked_add(market_state.court_period)
+            .ok_or(PredictionError::Overflow)?;
+        dispute.reveal_deadline = dispute
+            .commit_deadline
+            .checked_add(market_state.court_period)
+            .ok_or(PredictionError::Overflow)?;
+
+        emit!(PanelDrawnEvent {
+            prediction_id: prediction.id,
+            jurors,
+            commit_deadline: dispute.commit_deadline,
+            reveal_deadline: dispute.reveal_deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Records a juror's sealed vote, `court::vote_commitment(result, salt, juror)`.
+    pub fn commit_vote(ctx: Context<CastJurorVote>, commitment: [u8; 32]) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let dispute = &mut ctx.accounts.dispute;
+
+        require!(
+            dispute.phase == DisputePhase::Voting
+                && Clock::get()?.unix_timestamp < dispute.commit_deadline,
+            PredictionError::InvalidCourtPhase
+        );
+        let juror = ctx.accounts.juror.key();
+        let vote = dispute.find_vote(&juror)?;
+        require!(!vote.committed, PredictionError::AlreadyVoted);
+
+        vote.commitment = commitment;
+        vote.committed = true;
+
+        emit!(JurorVoteCommittedEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            juror,
+        });
+
+        Ok(())
+    }
+
+    /// Opens a committed vote. Reveals start at the commit deadline, or as
+    /// soon as the whole panel has committed.
+    pub fn reveal_vote(
+        ctx: Context<CastJurorVote>,
+        result: PredictionResult,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+        let prediction = &ctx.accounts.prediction;
+        let dispute = &mut ctx.accounts.dispute;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            dispute.phase == DisputePhase::Voting
+                && now < dispute.reveal_deadline
+                && (now >= dispute.commit_deadline
+                    || dispute.panel.iter().all(|vote| vote.committed)),
+            PredictionError::InvalidCourtPhase
+        );
+        require!(
+            prediction.accepts_result(result),
+            PredictionError::InvalidResult
+        );
+
+        let juror = ctx.accounts.juror.key();
+        let commitment = court::vote_commitment(&result, &salt, &juror)?;
+        let vote = dispute.find_vote(&juror)?;
+        require!(
+            vote.committed && !vote.revealed,
+            PredictionError::InvalidCommitment
+        );
+        require!(
+            vote.commitment == commitment,
+            PredictionError::InvalidCommitment
+        );
+
+        vote.revealed = true;
+        vote.result = result;
+
+        emit!(JurorVoteRevealedEvent {
+            prediction_id: prediction.id,
+            juror,
+            result,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the court once voting is over. A strict majority of the panel
+    /// becomes the verdict: jurors who voted otherwise or never revealed lose
+    /// their locked stake to the majority, and the bonds of an optimistic
+    /// dispute go to whichever side the verdict backs. Without a majority
+    /// the market stays disputed and a new court can be opened, but jurors
+    /// who never revealed still lose their stake to those who did, or to the
+    /// treasury if nobody revealed. An opener who never drew a panel
+    /// forfeits the court bond to the treasury.
+    pub fn settle_dispute(ctx: Context<SettleDispute>) -> Result<()> {
+        ctx.accounts.market_state.require_not_paused()?;
+
+        let dispute = &ctx.accounts.dispute;
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let finished = match dispute.phase {
+            // Past the SlotHashes window the draw slot can no longer be read
+            DisputePhase::Drawing => {
+                now >= dispute.commit_deadline
+                    || clock.slot > dispute.seed_slot.saturating_add(court::SLOT_HASHES_DEPTH)
+            }
+            DisputePhase::Voting => {
+                now >= dispute.reveal_deadline || dispute.panel.iter().all(|vote| vote.revealed)
+            }
+        };
+        require!(finished, PredictionError::CourtStillOpen);
+
+        let revealed: Vec<PredictionResult> = dispute
+            .panel
+            .iter()
+            .filter(|vote| vote.revealed)
+            .map(|vote| vote.result)
+            .collect();
+        let verdict = court::majority(&revealed, dispute.panel.len());
+
+        let stake = dispute.juror_stake;
+        let losers = dispute
+            .panel
+            .iter()
+            .filter(|vote| !court::rewarded(vote, verdict))
+            .count() as u64;
+        let winners = dispute.panel.len() as u64 - losers;
+        let slashed = stake.checked_mul(losers).ok_or(PredictionError::Overflow)?;
+        let share = slashed.checked_div(winners).unwrap_or(0);
+        let mut remainder = slashed.checked_rem(winners).unwrap_or(0);
+
+        let juror_pool = &mut ctx.accounts.juror_pool;
+        for vote in dispute.panel.iter() {
+            let index = juror_pool
+                .find_juror(&vote.juror)
+                .ok_or(PredictionError::NotJuror)?;
+            let entry = &mut juror_pool.jurors[index];
+            entry.locked -= stake;
+            if court::rewarded(vote, verdict) {
+                entry.stake = entry
+                    .stake
+                    .checked_add(share + remainder)
+                    .ok_or(PredictionError::Overflow)?;
+                remainder = 0;
+            } else {
+                entry.stake -= stake;
+            }
+        }
+        // Slashed jurors with nothing left leave the pool
+        juror_pool.jurors.retain(|entry| entry.stake > 0);
+        // With nobody revealing there is no one to share with
+        if winners == 0 {
+            transfer_lamports(
+                &juror_pool.to_account_info(),
+                &ctx.accounts.market_state.to_account_info(),
+                slashed,
+            )?;
+        }
+
+        // The verdict only applies if no one resolved the market meanwhile
+        let prediction = &mut ctx.accounts.prediction;
+        let applied = verdict.is_some() && prediction.state == PredictionState::Disputed;
+        if let (true, Some(verdict)) = (applied, verdict) {
+            prediction.settle(verdict)?;
+
+            if prediction.disputer != Pubkey::default() {
+                let (Some(proposer), Some(disputer)) =
+                    (&ctx.accounts.proposer, &ctx.accounts.disputer)
+                else {
+                    return err!(PredictionError::MissingBondAccounts);
+                };
+                let winner = if verdict == prediction.proposed_result {
+                    proposer.to_account_info()
+                } else {
+                    disputer.to_account_info()
+                };
+                let amount = prediction
+                    .resolution_bond
+                    .checked_mul(2)
+                    .ok_or(PredictionError::Overflow)?;
+                transfer_from_vault(
+                    &ctx.accounts.prediction,
+                    &ctx.accounts.vault,
+                    winner,
+                    &ctx.accounts.system_program,
+                    amount,
+                )?;
+            }
+        }
+
+        // The bond goes back to the opener with the account unless the seed
+        // was never revealed
+        let dispute = &ctx.accounts.dispute;
+        let forfeited_bond = if dispute.phase == DisputePhase::Drawing {
+            dispute.bond
+        } else {
+            0
+        };
+        transfer_lamports(
+            &dispute.to_account_info(),
+            &ctx.accounts.market_state.to_account_info(),
+            forfeited_bond,
+        )?;
+
+        emit!(DisputeSettledEvent {
+            prediction_id: ctx.accounts.prediction.id,
+            verdict,
+            applied,
+            slashed,
+            forfeited_bond,
+        });
+
+        Ok(())
+    }
+
+    /// Pays pending claims out of the vault. Each claimant is passed as a
+    /// writable remaining account, in the same order as `claim_indices`.
+    pub fn approve_claims<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ApproveClaims<'info>>,
+        claim_indices: Vec<u64>,
+    ) -> Result<()> {
+        let prediction = &ctx.accounts.prediction;
+        let claims = &mut ctx.accounts.claims;
+
+        require!(
+            claim_indices.len() == ctx.remaining_accounts.len(),
+            PredictionError::InvalidClaimIndex
+        );
+
+        for (&index, claimant) in claim_indices.iter().zip(ctx.remaining_accounts) {
+            require!(
+                (index as usize) < claims.pending_claims.len(),
+                PredictionError::InvalidClaimIndex
+            );
+            let claim = &mut claims.pending_claims[index as usize];
+            require!(
+                claim.state == ClaimState::Pending,
+                PredictionError::ClaimNotPending
+            );
+            require!(
+                claimant.key() == claim.user,
+                PredictionError::InvalidUserAccount
+            );
+
+            transfer_from_vault(
+                prediction,
+                &ctx.accounts.vault,
+                claimant.clone(),
+                &ctx.accounts.system_program,
+                claim.amount,
+            )?;
+            claim.state = ClaimState::Claimed;
+
+            emit!(ClaimApprovedEvent {
+                prediction_id: prediction.id,
+                user: claim.user,
+                amount: claim.amount,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = admin, space = MarketState::LEN)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        constraint = market_state.pending_admin != Pubkey::default() @ PredictionError::NoPendingAdmin,
+        constraint = market_state.pending_admin == pending_admin.key() @ PredictionError::NotAuthorized,
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub pending_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(authority: Pubkey)]
+pub struct SetRoles<'info> {
+    #[account(has_one = admin @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Role::LEN,
+        seeds = [b"role", market_state.key().as_ref(), authority.as_ref()],
+        bump
+    )]
+    pub role: Account<'info, Role>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_TREASURER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPause<'info> {
+    #[account(mut, has_one = guardian @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketState<'info> {
+    #[account(mut, has_one = admin @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(description: String, duration: i64, tags: Vec<String>, prediction_type: u8, options_count: u8, options: Vec<String>)]
+pub struct CreatePrediction<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init,
+        payer = authority,
+        space = Prediction::space(&description, &tags, options_count, &options),
+        seeds = [b"prediction", market_state.key().as_ref(), market_state.next_prediction_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// Creators without this role must post `market_state.creation_bond`.
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_CREATOR) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Option<Account<'info, Role>>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = token_vault,
+        token::token_program = token_program,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct Predict<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserPrediction::LEN,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump = prediction.token_vault_bump,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        mut,
+        seeds = [b"user", market_state.key().as_ref(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.market == market_state.key() @ PredictionError::InvalidUserAccount,
+    )]
+    pub user_account: Option<Account<'info, UserAccount>>,
+}
+
+impl<'info> Predict<'info> {
+    /// Moves a stake from the user into the market, in lamports or in the
+    /// market's collateral token.
+    fn deposit(&self, amount: u64) -> Result<()> {
+        if self.prediction.is_native() {
+            // Transfer SOL from user to the market vault
+            let cpi_context = CpiContext::new(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.user.to_account_info(),
+                    to: self.vault.to_account_info(),
+                },
+            );
+            return anchor_lang::system_program::transfer(cpi_context, amount);
+        }
+
+        let (mint, token_vault, token_program) = token_vault_accounts(
+            &self.prediction,
+            &self.collateral_mint,
+            &self.token_vault,
+            &self.token_program,
+        )?;
+        let user_token_account = self
+            .user_token_account
+            .as_ref()
+            .ok_or(PredictionError::MissingTokenAccounts)?;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: user_token_account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: token_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+            mint.decimals,
+        )
+    }
+
+    /// Counts the user's first bet on this market towards their profile, if
+    /// they have registered one.
+    fn count_prediction(&mut self) -> Result<()> {
+        if self.user_prediction.bet_count > 0 {
+            return Ok(());
+        }
+        if let Some(user_account) = self.user_account.as_mut() {
+            user_account.total_predictions = user_account
+                .total_predictions
+                .checked_add(1)
+                .ok_or(PredictionError::Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeLmsr<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_CREATOR) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLmsrSurplus<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = prediction.creator @ PredictionError::NotAuthorized)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TradeShares<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserPrediction::LEN,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        init,
+        payer = authority,
+        space = Pool::LEN,
+        seeds = [b"pool", prediction.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_CREATOR) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PoolTrade<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"pool", prediction.key().as_ref()],
+        bump = pool.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PoolPosition::LEN,
+        seeds = [b"pool_position", pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, PoolPosition>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeOrderBook<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        init,
+        payer = authority,
+        space = OrderBook::LEN,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump
+    )]
+    pub order_book: Account<'info, OrderBook>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_CREATOR) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump = order_book.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub order_book: Account<'info, OrderBook>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OrderPosition::LEN,
+        seeds = [b"order_position", prediction.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, OrderPosition>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump = order_book.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub order_book: Account<'info, OrderBook>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimOrder<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump = order_book.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub order_book: Account<'info, OrderBook>,
+    #[account(mut)]
+    pub owner: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump = order_book.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub order_book: Account<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"order_position", prediction.key().as_ref(), yes_owner.key().as_ref()],
+        bump
+    )]
+    pub yes_position: Account<'info, OrderPosition>,
+    #[account(
+        mut,
+        seeds = [b"order_position", prediction.key().as_ref(), no_owner.key().as_ref()],
+        bump
+    )]
+    pub no_position: Account<'info, OrderPosition>,
+    /// CHECK: receives the YES order's price improvement; checked against the order owner
+    #[account(mut)]
+    pub yes_owner: UncheckedAccount<'info>,
+    /// CHECK: receives the NO order's price improvement; checked against the order owner
+    #[account(mut)]
+    pub no_owner: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemOrderPosition<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"order_position", prediction.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = position.user == user.key() @ PredictionError::NotAuthorized,
+    )]
+    pub position: Account<'info, OrderPosition>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePrediction<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_PAUSER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureCreatorFee<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(address = prediction.creator @ PredictionError::NotAuthorized)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCreatorFee<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = prediction.creator @ PredictionError::NotAuthorized)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump = prediction.token_vault_bump,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::authority = creator)]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePredictionFee<'info> {
+    #[account(has_one = admin @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigurePriceFeed<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    /// CHECK: owned by the trusted oracle program; parsed by `price_feed::load`
+    #[account(
+        owner = market_state.oracle_program @ PredictionError::InvalidPriceFeed,
+        constraint = market_state.oracle_program != Pubkey::default() @ PredictionError::InvalidPriceFeed,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_RESOLVER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFromFeed<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    /// CHECK: must be the feed pinned by `set_price_feed`; parsed by `price_feed::load`
+    #[account(
+        address = prediction.price_feed @ PredictionError::InvalidPriceFeed,
+        owner = market_state.oracle_program @ PredictionError::InvalidPriceFeed,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveWithAttestation<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    /// CHECK: the instructions sysvar, pinned by address
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeResolverQuorum<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        init,
+        payer = authority,
+        space = ResolverQuorum::LEN,
+        seeds = [b"quorum", prediction.key().as_ref()],
+        bump
+    )]
+    pub quorum: Account<'info, ResolverQuorum>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_CREATOR) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitResolverVote<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"quorum", prediction.key().as_ref()],
+        bump = quorum.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub quorum: Account<'info, ResolverQuorum>,
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolvePrediction<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_RESOLVER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    /// Required for order book markets, whose open orders are refunded.
+    #[account(
+        mut,
+        seeds = [b"order_book", prediction.key().as_ref()],
+        bump = order_book.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub order_book: Option<Account<'info, OrderBook>>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: only credited; pinned to `market_state.fee_recipient`
+    #[account(mut, address = market_state.fee_recipient @ PredictionError::InvalidFeeRecipient)]
+    pub fee_recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_TREASURER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump = prediction.token_vault_bump,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBet<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = user_prediction.user == user.key() @ PredictionError::NotAuthorized,
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump = prediction.token_vault_bump,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = user_prediction.user == user.key() @ PredictionError::NotAuthorized,
+        constraint = user_prediction.prediction_id == prediction.id @ PredictionError::InvalidPrediction,
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub collateral_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"token_vault", prediction.key().as_ref()],
+        bump = prediction.token_vault_bump,
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterUser<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init,
+        payer = user,
+        space = UserAccount::LEN,
+        seeds = [b"user", market_state.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAlias<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"user", market_state.key().as_ref(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.market == market_state.key() @ PredictionError::InvalidUserAccount,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(has_one = admin @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init,
+        payer = admin,
+        space = Leaderboard::LEN,
+        seeds = [b"leaderboard", market_state.key().as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResetSeason<'info> {
+    #[account(has_one = admin @ PredictionError::NotAuthorized)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"leaderboard", market_state.key().as_ref()],
+        bump = leaderboard.bump,
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLeaderboard<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"leaderboard", market_state.key().as_ref()],
+        bump = leaderboard.bump,
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReputation<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user_account.user.as_ref()],
+        bump,
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    #[account(
+        mut,
+        seeds = [b"user", market_state.key().as_ref(), user_account.user.as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.market == market_state.key() @ PredictionError::InvalidUserAccount,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitClaim<'info> {
+    #[account(address = prediction.market @ PredictionError::InvalidPrediction)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"claims", prediction.key().as_ref()],
+        bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub claims: Account<'info, Claims>,
+    #[account(
+        mut,
+        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = user_prediction.user == user.key() @ PredictionError::NotAuthorized,
+    )]
+    pub user_prediction: Account<'info, UserPrediction>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeResult<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeResult<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub disputer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeResult<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = prediction.proposer @ PredictionError::NotAuthorized)]
+    pub proposer: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Arbitrate<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = prediction.proposer @ PredictionError::NotAuthorized)]
+    pub proposer: SystemAccount<'info>,
+    #[account(mut, address = prediction.disputer @ PredictionError::NotAuthorized)]
+    pub disputer: SystemAccount<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_RESOLVER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
-pub struct DistributeRewards<'info> {
+pub struct RegisterJuror<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init_if_needed,
+        payer = juror,
+        space = JurorPool::LEN,
+        seeds = [b"jurors", market_state.key().as_ref()],
+        bump
+    )]
+    pub juror_pool: Account<'info, JurorPool>,
+    #[account(mut)]
+    pub juror: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawJurorStake<'info> {
+    pub market_state: Account<'info, MarketState>,
     #[account(
         mut,
+        seeds = [b"jurors", market_state.key().as_ref()],
+        bump = juror_pool.bump,
+    )]
+    pub juror_pool: Account<'info, JurorPool>,
+    #[account(mut)]
+    pub juror: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
         seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        init,
+        payer = opener,
+        space = Dispute::LEN,
+        seeds = [b"dispute", prediction.key().as_ref()],
         bump
     )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub opener: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DrawPanel<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"dispute", prediction.key().as_ref()],
+        bump = dispute.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        seeds = [b"jurors", market_state.key().as_ref()],
+        bump = juror_pool.bump,
+    )]
+    pub juror_pool: Account<'info, JurorPool>,
+    /// CHECK: the SlotHashes sysvar, read raw in `court::slot_hash_at`
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CastJurorVote<'info> {
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
     pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"dispute", prediction.key().as_ref()],
+        bump = dispute.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    pub juror: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDispute<'info> {
     #[account(mut)]
     pub market_state: Account<'info, MarketState>,
-    #[account(mut, constraint = admin.key() == market_state.admin @ PredictionError::NotAuthorized)]
-    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"dispute", prediction.key().as_ref()],
+        bump = dispute.bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+        has_one = opener @ PredictionError::NotAuthorized,
+        close = opener,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        seeds = [b"jurors", market_state.key().as_ref()],
+        bump = juror_pool.bump,
+    )]
+    pub juror_pool: Account<'info, JurorPool>,
+    #[account(mut)]
+    pub opener: SystemAccount<'info>,
+    #[account(mut, address = prediction.proposer @ PredictionError::NotAuthorized)]
+    pub proposer: Option<SystemAccount<'info>>,
+    #[account(mut, address = prediction.disputer @ PredictionError::NotAuthorized)]
+    pub disputer: Option<SystemAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleCreatorBond<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        mut,
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut, address = prediction.creator @ PredictionError::NotAuthorized)]
+    pub creator: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveClaims<'info> {
+    #[account(mut)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
+        constraint = prediction.market == market_state.key() @ PredictionError::InvalidPrediction,
+    )]
+    pub prediction: Account<'info, Prediction>,
+    #[account(
+        mut,
+        seeds = [b"vault", prediction.key().as_ref()],
+        bump = prediction.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"claims", prediction.key().as_ref()],
+        bump,
+        has_one = prediction @ PredictionError::InvalidPrediction,
+    )]
+    pub claims: Account<'info, Claims>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"role", market_state.key().as_ref(), authority.key().as_ref()],
+        bump = role.bump,
+        constraint = role.has(ROLE_TREASURER) @ PredictionError::NotAuthorized,
+    )]
+    pub role: Account<'info, Role>,
     pub system_program: Program<'info, System>,
 }
 
+#[account]
+pub struct MarketState {
+    pub admin: Pubkey,
+    pub next_prediction_id: u64,
+    pub cancel_penalty_bps: u16,
+    pub cancel_cutoff: i64,
+    pub guardian: Pubkey,
+    pub paused: bool,
+    pub pending_admin: Pubkey,
+    pub creation_bond: u64,
+    pub proposal_bond: u64,
+    pub challenge_window: i64,
+    pub proposer_reward: u64,
+    pub oracle: Pubkey,
+    pub juror_stake: u64,
+    pub panel_size: u8,
+    pub court_period: i64,
+    pub court_bond: u64,
+    pub protocol_fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub max_creator_fee_bps: u16,
+    pub oracle_program: Pubkey,
+}
+
+impl MarketState {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // admin
+        + 8 // next_prediction_id
+        + 2 // cancel_penalty_bps
+        + 8 // cancel_cutoff
+        + 32 // guardian
+        + 1 // paused
+        + 32 // pending_admin
+        + 8 // creation_bond
+        + 8 // proposal_bond
+        + 8 // challenge_window
+        + 8 // proposer_reward
+        + 32 // oracle
+        + 8 // juror_stake
+        + 1 // panel_size
+        + 8 // court_period
+        + 8 // court_bond
+        + 2 // protocol_fee_bps
+        + 32 // fee_recipient
+        + 2 // max_creator_fee_bps
+        + 32; // oracle_program
+
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.paused, PredictionError::MarketPaused);
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Role {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub roles: u8,
+    pub bump: u8,
+}
+
+impl Role {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // market
+        + 32 // authority
+        + 1 // roles
+        + 1; // bump
+
+    pub fn has(&self, role: u8) -> bool {
+        self.roles & role == role
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct Prediction {
+    pub id: u64,
+    pub market: Pubkey,
+    pub bump: u8,
+    pub state: PredictionState,
+    pub description: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total_votes: u64,
+    pub yes_votes: u64,
+    pub no_votes: u64,
+    pub yes_amount: u64,
+    pub no_amount: u64,
+    pub result: PredictionResult,
+    pub total_amount: u64,
+    pub prediction_type: u8,
+    pub options_count: u8,
+    pub tags: Vec<String>,
+    pub reward_info: Option<RewardInfo>,
+    pub reward_per_lamport: u64,
+    pub rewards_distributed: bool,
+    pub options: Vec<String>,
+    pub option_votes: Vec<u64>,
+    pub option_amounts: Vec<u64>,
+    pub scalar_lower_bound: i64,
+    pub scalar_upper_bound: i64,
+    pub short_reward_per_lamport: u64,
+    pub market_mode: u8,
+    pub lmsr_liquidity: u64,
+    pub lmsr_shares: Vec<u64>,
+    pub collateral_mint: Pubkey,
+    pub collateral_decimals: u8,
+    pub token_vault_bump: u8,
+    pub vault_bump: u8,
+    pub cancel_penalty_bps: u16,
+    pub cancel_deadline: i64,
+    pub paused_at: i64,
+    pub creator: Pubkey,
+    pub creator_bond: u64,
+    pub proposed_result: PredictionResult,
+    pub proposer: Pubkey,
+    pub disputer: Pubkey,
+    pub resolution_bond: u64,
+    pub challenge_deadline: i64,
+    pub price_feed: Pubkey,
+    pub feed_operator: u8,
+    pub feed_threshold: i64,
+    pub feed_exponent: i32,
+    pub feed_max_staleness: i64,
+    pub feed_max_confidence_bps: u16,
+    pub resolver_quorum: bool,
+    pub protocol_fee_bps: u16,
+    pub creator_fee_bps: u16,
+    pub creator_fee: u64,
+    pub lmsr_surplus_withdrawn: bool,
+    pub refund_basis: u64,
+}
+
+impl Prediction {
+    pub const BASE_LEN: usize = 8 // discriminator
+        + 8 // id
+        + 32 // market
+        + 1 // bump
+        + 1 // state
+        + 4 // description prefix
+        + 8 // start_time
+        + 8 // end_time
+        + 8 // total_votes
+        + 8 // yes_votes
+        + 8 // no_votes
+        + 8 // yes_amount
+        + 8 // no_amount
+        + 9 // result
+        + 8 // total_amount
+        + 1 // prediction_type
+        + 1 // options_count
+        + 4 // tags prefix
+        + 1 + 16 // reward_info
+        + 8 // reward_per_lamport
+        + 1 // rewards_distributed
+        + 4 // options prefix
+        + 4 // option_votes prefix
+        + 4 // option_amounts prefix
+        + 8 // scalar_lower_bound
+        + 8 // scalar_upper_bound
+        + 8 // short_reward_per_lamport
+        + 1 // market_mode
+        + 8 // lmsr_liquidity
+        + 4 // lmsr_shares prefix
+        + 32 // collateral_mint
+        + 1 // collateral_decimals
+        + 1 // token_vault_bump
+        + 1 // vault_bump
+        + 2 // cancel_penalty_bps
+        + 8 // cancel_deadline
+        + 8 // paused_at
+        + 32 // creator
+        + 8 // creator_bond
+        + 9 // proposed_result
+        + 32 // proposer
+        + 32 // disputer
+        + 8 // resolution_bond
+        + 8 // challenge_deadline
+        + 32 // price_feed
+        + 1 // feed_operator
+        + 8 // feed_threshold
+        + 4 // feed_exponent
+        + 8 // feed_max_staleness
+        + 2 // feed_max_confidence_bps
+        + 1 // resolver_quorum
+        + 2 // protocol_fee_bps
+        + 2 // creator_fee_bps
+        + 8 // creator_fee
+        + 1 // lmsr_surplus_withdrawn
+        + 8; // refund_basis
+
+    pub fn space(
+        description: &str,
+        tags: &[String],
+        options_count: u8,
+        options: &[String],
+    ) -> usize {
+        Self::BASE_LEN
+            + description.len()
+            + tags.iter().map(|tag| 4 + tag.len()).sum::<usize>()
+            + options.iter().map(|option| 4 + option.len()).sum::<usize>()
+            + options.len() * (8 + 8)
+            + options_count as usize * 8
+    }
+
+    /// Records the final result, voiding the market for `Invalid`.
+    pub fn settle(&mut self, result: PredictionResult) -> Result<()> {
+        self.result = result;
+        if result == PredictionResult::Invalid {
+            self.state = PredictionState::Cancelled;
+        } else {
+            self.state = PredictionState::Resolved;
+        }
+        Ok(())
+    }
+
+    /// Splits the pool at settlement into `(reward_pool, protocol_fee,
+    /// creator_fee)`. The creator's cut comes out of the protocol fee, so
+    /// together they never exceed `protocol_fee_bps` of the pool.
+    pub fn fee_split(&self) -> Result<(u64, u64, u64)> {
+        let fee = if self.market_mode == MARKET_MODE_LMSR {
+            0 // LMSR shares redeem 1:1 and the maker's spread stays in the market
+        } else {
+            mul_div(
+                self.total_amount,
+                self.protocol_fee_bps as u64,
+                BPS_DENOMINATOR as u128,
+            )?
+        };
+        let creator_fee = mul_div(fee, self.creator_fee_bps as u64, BPS_DENOMINATOR as u128)?;
+        Ok((self.total_amount - fee, fee - creator_fee, creator_fee))
+    }
+
+    /// Hands out the creator's share of the fee, once, after distribution.
+    pub fn take_creator_fee(&mut self) -> Result<u64> {
+        require!(
+            self.rewards_distributed,
+            PredictionError::RewardsNotDistributed
+        );
+        let amount = self.creator_fee;
+        require!(amount > 0, PredictionError::NoCreatorFee);
+        self.creator_fee = 0;
+        Ok(amount)
+    }
+
+    /// Lamports available to refund `refund_basis` in a voided market.
+    /// Parimutuel pools keep cancellation penalties on top of the basis;
+    /// an LMSR book has the trades' net revenue plus the subsidy.
+    pub fn refund_pool(&self) -> Result<u64> {
+        if self.market_mode == MARKET_MODE_LMSR {
+            let backing = lmsr::subsidy(self.lmsr_liquidity, self.options_count)?
+                .checked_add(self.total_amount)
+                .ok_or(PredictionError::Overflow)?;
+            Ok(backing.min(self.refund_basis))
+        } else {
+            Ok(self.total_amount)
+        }
+    }
+
+    /// A voided position's pro-rata share of `refund_pool`.
+    pub fn refund_for(&self, user_prediction: &UserPrediction) -> Result<u64> {
+        if self.refund_basis == 0 {
+            return Ok(0);
+        }
+        mul_div(
+            user_prediction.amount,
+            self.refund_pool()?,
+            self.refund_basis as u128,
+        )
+    }
+
+    pub fn require_active(&self) -> Result<()> {
+        match self.state {
+            PredictionState::Active => Ok(()),
+            PredictionState::Paused => Err(PredictionError::PredictionPaused.into()),
+            _ => Err(PredictionError::PredictionNotActive.into()),
+        }
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.collateral_mint == Pubkey::default()
+    }
+
+    /// Outcome index used by LMSR share books. Binary markets store YES
+    /// shares at index 0 and NO shares at index 1.
+    pub fn winning_outcome(&self) -> Result<u8> {
+        match self.result {
+            PredictionResult::True => Ok(0),
+            PredictionResult::False => Ok(1),
+            PredictionResult::Outcome(index) => Ok(index),
+            PredictionResult::Scalar(_)
+            | PredictionResult::Undefined
+            | PredictionResult::Invalid => Err(PredictionError::InvalidResult.into()),
+        }
+    }
+
+    pub fn accepts_result(&self, result: PredictionResult) -> bool {
+        match (self.prediction_type, result) {
+            (PREDICTION_TYPE_BINARY, PredictionResult::True | PredictionResult::False) => true,
+            (PREDICTION_TYPE_CATEGORICAL, PredictionResult::Outcome(index)) => {
+                index < self.options_count
+            }
+            (PREDICTION_TYPE_SCALAR, PredictionResult::Scalar(_)) => true,
+            (_, PredictionResult::Invalid) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_winner(&self, user_prediction: &UserPrediction) -> Result<bool> {
+        if self.market_mode == MARKET_MODE_LMSR {
+            let outcome = self.winning_outcome()? as usize;
+            return Ok(user_prediction.shares.get(outcome).copied().unwrap_or(0) > 0);
+        }
+        if let PredictionResult::Scalar(_) = self.result {
+            let (long_rate, short_rate) = self.scalar_rates()?;
+            return Ok((user_prediction.yes_amount > 0 && long_rate > 0)
+                || (user_prediction.no_amount > 0 && short_rate > 0));
+        }
+        Ok(self.winning_stake(user_prediction)? > 0)
+    }
+
+    /// Accumulated stake the user holds on the winning side.
+    pub fn winning_stake(&self, user_prediction: &UserPrediction) -> Result<u64> {
+        match self.result {
+            PredictionResult::True => Ok(user_prediction.yes_amount),
+            PredictionResult::False => Ok(user_prediction.no_amount),
+            PredictionResult::Outcome(index) => Ok(user_prediction
+                .option_amounts
+                .get(index as usize)
+                .copied()
+                .unwrap_or(0)),
+            PredictionResult::Scalar(_)
+            | PredictionResult::Undefined
+            | PredictionResult::Invalid => Err(PredictionError::InvalidResult.into()),
+        }
+    }
+
+    pub fn winning_amount(&self) -> Result<u64> {
+        match self.result {
+            PredictionResult::True => Ok(self.yes_amount),
+            PredictionResult::False => Ok(self.no_amount),
+            PredictionResult::Outcome(index) => self
+                .option_amounts
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| PredictionError::InvalidResult.into()),
+            PredictionResult::Scalar(_)
+            | PredictionResult::Undefined
+            | PredictionResult::Invalid => Err(PredictionError::InvalidResult.into()),
+        }
+    }
+
+    pub fn reward_for(&self, user_prediction: &UserPrediction) -> Result<u64> {
+        if self.market_mode == MARKET_MODE_LMSR {
+            let outcome = self.winning_outcome()? as usize;
+            return Ok(user_prediction.shares.get(outcome).copied().unwrap_or(0));
+        }
+        let reward = if self.prediction_type == PREDICTION_TYPE_SCALAR {
+            let long = (user_prediction.yes_amount as u128)
+                .checked_mul(self.reward_per_lamport as u128)
+                .ok_or(PredictionError::Overflow)?;
+            let short = (user_prediction.no_amount as u128)
+                .checked_mul(self.short_reward_per_lamport as u128)
+                .ok_or(PredictionError::Overflow)?;
+            long.checked_add(short).ok_or(PredictionError::Overflow)? / REWARD_PRECISION as u128
+        } else {
+            (self.winning_stake(user_prediction)? as u128)
+                .checked_mul(self.reward_per_lamport as u128)
+                .ok_or(PredictionError::Overflow)?
+                / REWARD_PRECISION as u128
+        };
+        u64::try_from(reward).map_err(|_| PredictionError::Overflow.into())
+    }
+
+    pub fn scalar_value(&self) -> Result<i64> {
+        match self.result {
+            PredictionResult::Scalar(value) => Ok(value),
+            _ => Err(PredictionError::InvalidResult.into()),
+        }
+    }
+
+    /// Share of the reward pool owed to the long side, scaled by
+    /// `REWARD_PRECISION`. Values outside the range are clamped.
+    pub fn scalar_long_share(&self) -> Result<u64> {
+        let value = self
+            .scalar_value()?
+            .clamp(self.scalar_lower_bound, self.scalar_upper_bound);
+        let offset = (value as i128 - self.scalar_lower_bound as i128) as u128;
+        let width = (self.scalar_upper_bound as i128 - self.scalar_lower_bound as i128) as u128;
+        let share = offset
+            .checked_mul(REWARD_PRECISION as u128)
+            .ok_or(PredictionError::Overflow)?
+            / width;
+        Ok(share as u64)
+    }
+
+    /// The long and short rates `reward_for` pays: the distributed ones, or
+    /// before distribution the ones the whole pool would produce.
+    pub fn scalar_rates(&self) -> Result<(u64, u64)> {
+        if self.rewards_distributed {
+            return Ok((self.reward_per_lamport, self.short_reward_per_lamport));
+        }
+        self.scalar_reward_rates(self.total_amount)
+    }
+
+    /// Reward per lamport for the long and short sides. If one side has no
+    /// stake its share of the pool goes to the other side.
+    pub fn scalar_reward_rates(&self, reward_pool: u64) -> Result<(u64, u64)> {
+        let long_share = self.scalar_long_share()?;
+        let (long_pool, short_pool) = match (self.yes_amount, self.no_amount) {
+            (0, _) => (0, reward_pool as u128),
+            (_, 0) => (reward_pool as u128, 0),
+            _ => {
+                let long_pool = (reward_pool as u128)
+                    .checked_mul(long_share as u128)
+                    .ok_or(PredictionError::Overflow)?
+                    / REWARD_PRECISION as u128;
+                (long_pool, reward_pool as u128 - long_pool)
+            }
+        };
+        let rate = |pool: u128, stake: u64| -> Result<u64> {
+            if stake == 0 {
+                return Ok(0);
+            }
+            let rate = pool
+                .checked_mul(REWARD_PRECISION as u128)
+                .ok_or(PredictionError::Overflow)?
+                / stake as u128;
+            u64::try_from(rate).map_err(|_| PredictionError::Overflow.into())
+        };
+        Ok((
+            rate(long_pool, self.yes_amount)?,
+            rate(short_pool, self.no_amount)?,
+        ))
+    }
+}
+
+#[account]
+pub struct Pool {
+    pub prediction: Pubkey,
+    pub yes_reserve: u64,
+    pub no_reserve: u64,
+    pub total_shares: u64,
+    pub fee_bps: u16,
+    pub fee_per_share: u128,
+    pub bump: u8,
+    /// Sum of the positions' cost bases.
+    pub total_cost: u64,
+    /// Lamports positions have put into the vault less what they took out.
+    pub collateral: u64,
+}
+
+impl Pool {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 2 + 16 + 1 + 8 + 8;
+
+    /// Returns `(chosen side, other side)` reserves for a YES or NO trade.
+    pub fn reserves_mut(&mut self, verdict: bool) -> (&mut u64, &mut u64) {
+        if verdict {
+            (&mut self.yes_reserve, &mut self.no_reserve)
+        } else {
+            (&mut self.no_reserve, &mut self.yes_reserve)
+        }
+    }
+
+    /// Mints `invested` complete sets, then takes out enough of the chosen
+    /// side to restore x * y = k. Returns the tokens bought.
+    pub fn buy(&mut self, verdict: bool, invested: u64) -> Result<u64> {
+        let (bought, other) = self.reserves_mut(verdict);
+        let invariant = (*bought as u128)
+            .checked_mul(*other as u128)
+            .ok_or(PredictionError::Overflow)?;
+        let bought_after_mint = bought
+            .checked_add(invested)
+            .ok_or(PredictionError::Overflow)?;
+        *other = other
+            .checked_add(invested)
+            .ok_or(PredictionError::Overflow)?;
+        let bought_final = div_ceil(invariant, *other as u128)?;
+        let tokens = bought_after_mint
+            .checked_sub(bought_final)
+            .ok_or(PredictionError::Overflow)?;
+        *bought = bought_final;
+        Ok(tokens)
+    }
+
+    /// Burns `gross` complete sets, paying for them with enough of the sold
+    /// side to restore x * y = k. Returns the tokens the seller gives up.
+    pub fn sell(&mut self, verdict: bool, gross: u64) -> Result<u64> {
+        let (sold, other) = self.reserves_mut(verdict);
+        let invariant = (*sold as u128)
+            .checked_mul(*other as u128)
+            .ok_or(PredictionError::Overflow)?;
+        require!(*other > gross, PredictionError::InsufficientLiquidity);
+        *other -= gross;
+        let sold_final = div_ceil(invariant, *other as u128)?;
+        let tokens = sold_final
+            .checked_add(gross)
+            .ok_or(PredictionError::Overflow)?
+            .checked_sub(*sold)
+            .ok_or(PredictionError::Overflow)?;
+        *sold = sold_final;
+        Ok(tokens)
+    }
+
+    pub fn collect_fee(&mut self, fee: u64) -> Result<()> {
+        let increase = (fee as u128)
+            .checked_mul(FEE_PER_SHARE_PRECISION)
+            .ok_or(PredictionError::Overflow)?
+            .checked_div(self.total_shares as u128)
+            .ok_or(PredictionError::InsufficientLiquidity)?;
+        self.fee_per_share = self
+            .fee_per_share
+            .checked_add(increase)
+            .ok_or(PredictionError::Overflow)?;
+        Ok(())
+    }
+
+    pub fn fee_debt_for(&self, shares: u64) -> Result<u128> {
+        Ok((shares as u128)
+            .checked_mul(self.fee_per_share)
+            .ok_or(PredictionError::Overflow)?
+            / FEE_PER_SHARE_PRECISION)
+    }
+
+    /// Fees earned by `position` since it last changed its LP shares.
+    pub fn settle_fees(&self, position: &mut PoolPosition) -> Result<u64> {
+        let earned = self.fee_debt_for(position.lp_shares)?;
+        let pending = earned.saturating_sub(position.fee_debt);
+        position.fee_debt = earned;
+        u64::try_from(pending).map_err(|_| PredictionError::Overflow.into())
+    }
+
+    /// Records lamports `position` paid into the vault.
+    pub fn record_deposit(&mut self, position: &mut PoolPosition, amount: u64) -> Result<()> {
+        position.cost = position
+            .cost
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        self.total_cost = self
+            .total_cost
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        self.collateral = self
+            .collateral
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        Ok(())
+    }
+
+    /// Records lamports paid out to `position`, which lower its cost basis
+    /// down to zero. Anything above that is profit taken from other positions.
+    pub fn record_withdrawal(&mut self, position: &mut PoolPosition, amount: u64) -> Result<()> {
+        let recovered = position.cost.min(amount);
+        position.cost -= recovered;
+        self.total_cost -= recovered;
+        self.collateral = self
+            .collateral
+            .checked_sub(amount)
+            .ok_or(PredictionError::Overflow)?;
+        Ok(())
+    }
+
+    /// What a voided market returns to `position`: its cost basis, scaled
+    /// down pro rata when earlier exits took out more than they paid in.
+    pub fn refund_for(&self, position: &PoolPosition) -> Result<u64> {
+        if self.total_cost == 0 {
+            return Ok(0);
+        }
+        mul_div(position.cost, self.collateral, self.total_cost as u128)
+    }
+}
+
+#[account]
+pub struct PoolPosition {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub yes_tokens: u64,
+    pub no_tokens: u64,
+    pub lp_shares: u64,
+    pub fee_debt: u128,
+    /// Lamports paid in less lamports taken out, floored at zero.
+    pub cost: u64,
+}
+
+impl PoolPosition {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 16 + 8;
+
+    pub fn tokens_mut(&mut self, verdict: bool) -> &mut u64 {
+        if verdict {
+            &mut self.yes_tokens
+        } else {
+            &mut self.no_tokens
+        }
+    }
+}
+
+#[account]
+pub struct OrderBook {
+    pub prediction: Pubkey,
+    pub next_order_id: u64,
+    pub orders: Vec<Order>,
+    pub bump: u8,
+}
+
+impl OrderBook {
+    pub const LEN: usize = 8 + 32 + 8 + 4 + MAX_OPEN_ORDERS * Order::LEN + 1;
+
+    pub fn find_order(&self, order_id: u64) -> Result<usize> {
+        self.orders
+            .iter()
+            .position(|order| order.id == order_id)
+            .ok_or_else(|| PredictionError::OrderNotFound.into())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Order {
+    pub id: u64,
+    pub owner: Pubkey,
+    pub verdict: bool,
+    pub price_bps: u16,
+    pub quantity: u64,
+}
+
+impl Order {
+    pub const LEN: usize = 8 + 32 + 1 + 2 + 8;
+}
+
+#[account]
+pub struct ResolverQuorum {
+    pub prediction: Pubkey,
+    pub threshold: u8,
+    pub resolvers: Vec<Pubkey>,
+    pub votes: Vec<ResolverVote>,
+    pub bump: u8,
+}
+
+impl ResolverQuorum {
+    pub const LEN: usize =
+        8 + 32 + 1 + 4 + 32 * MAX_RESOLVERS + 4 + MAX_RESOLVERS * ResolverVote::LEN + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ResolverVote {
+    pub resolver: Pubkey,
+    pub result: PredictionResult,
+}
+
+impl ResolverVote {
+    pub const LEN: usize = 32 + 9;
+}
+
+#[account]
+pub struct JurorPool {
+    pub market: Pubkey,
+    pub jurors: Vec<JurorEntry>,
+    pub bump: u8,
+}
+
+impl JurorPool {
+    pub const LEN: usize = 8 + 32 + 4 + MAX_JURORS * JurorEntry::LEN + 1;
+
+    pub fn find_juror(&self, authority: &Pubkey) -> Option<usize> {
+        self.jurors
+            .iter()
+            .position(|entry| entry.authority == *authority)
+    }
+}
+
+/// A registered juror. `locked` is the part of `stake` at risk in open
+/// disputes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct JurorEntry {
+    pub authority: Pubkey,
+    pub stake: u64,
+    pub locked: u64,
+}
+
+impl JurorEntry {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+#[account]
+pub struct Dispute {
+    pub prediction: Pubkey,
+    pub opener: Pubkey,
+    pub seed_commitment: [u8; 32],
+    pub seed_slot: u64,
+    pub phase: DisputePhase,
+    pub commit_deadline: i64,
+    pub reveal_deadline: i64,
+    pub juror_stake: u64,
+    pub bond: u64,
+    pub panel: Vec<JurorVote>,
+    pub bump: u8,
+}
+
+impl Dispute {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // prediction
+        + 32 // opener
+        + 32 // seed_commitment
+        + 8 // seed_slot
+        + 1 // phase
+        + 8 // commit_deadline
+        + 8 // reveal_deadline
+        + 8 // juror_stake
+        + 8 // bond
+        + 4 + MAX_PANEL_SIZE * JurorVote::LEN // panel
+        + 1; // bump
+
+    pub fn find_vote(&mut self, juror: &Pubkey) -> Result<&mut JurorVote> {
+        self.panel
+            .iter_mut()
+            .find(|vote| vote.juror == *juror)
+            .ok_or_else(|| PredictionError::NotJuror.into())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct JurorVote {
+    pub juror: Pubkey,
+    pub commitment: [u8; 32],
+    pub committed: bool,
+    pub revealed: bool,
+    pub result: PredictionResult,
+}
+
+impl JurorVote {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 9;
+}
+
+#[account]
+pub struct OrderPosition {
+    pub prediction: Pubkey,
+    pub user: Pubkey,
+    pub yes_contracts: u64,
+    pub no_contracts: u64,
+    pub cost: u64,
+}
+
+impl OrderPosition {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ScalarRange {
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct RewardInfo {
+    pub reward_per_lamport: u64,
+    pub total_reward_pool: u64,
+}
+
+#[account]
+pub struct UserAccount {
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub alias: String,
+    pub rank: u64,
+    pub reputation: u64,
+    pub total_predictions: u64,
+    pub scored_predictions: u64,
+    pub correct_predictions: u64,
+    pub rank_season: u32,
+    pub bump: u8,
+}
+
+impl UserAccount {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // user
+        + 32 // market
+        + 4 + MAX_ALIAS_LENGTH // alias
+        + 8 // rank
+        + 8 // reputation
+        + 8 // total_predictions
+        + 8 // scored_predictions
+        + 8 // correct_predictions
+        + 4 // rank_season
+        + 1; // bump
+}
+
+#[account]
+pub struct Leaderboard {
+    pub market: Pubkey,
+    pub season: u32,
+    pub season_started_at: i64,
+    pub updated_at: i64,
+    pub entries: Vec<LeaderboardEntry>,
+    pub bump: u8,
+}
+
+impl Leaderboard {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // market
+        + 4 // season
+        + 8 // season_started_at
+        + 8 // updated_at
+        + 4 + LEADERBOARD_SIZE * LeaderboardEntry::LEN // entries
+        + 1; // bump
+
+    /// Moves `user` to its place by `reputation`, behind existing ties, and
+    /// drops whoever falls past the last slot.
+    pub fn insert(&mut self, user: Pubkey, reputation: u64) {
+        self.remove(&user);
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.reputation < reputation)
+            .unwrap_or(self.entries.len());
+        self.entries
+            .insert(position, LeaderboardEntry { user, reputation });
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+
+    pub fn remove(&mut self, user: &Pubkey) {
+        self.entries.retain(|entry| entry.user != *user);
+    }
+
+    /// One-based rank of `user`, or 0 when not on the board.
+    pub fn rank_of(&self, user: &Pubkey) -> u64 {
+        self.entries
+            .iter()
+            .position(|entry| entry.user == *user)
+            .map_or(0, |index| index as u64 + 1)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LeaderboardEntry {
+    pub user: Pubkey,
+    pub reputation: u64,
+}
+
+impl LeaderboardEntry {
+    pub const LEN: usize = 32 + 8;
+}
+
+#[account]
+#[derive(Default)]
+pub struct UserPrediction {
+    pub user: Pubkey,
+    pub prediction_id: u64,
+    pub amount: u64,
+    pub yes_amount: u64,
+    pub no_amount: u64,
+    pub reward_claimed: bool,
+    pub option_amounts: Vec<u64>,
+    pub shares: Vec<u64>,
+    pub bet_count: u32,
+    pub first_bet_at: i64,
+    pub last_bet_at: i64,
+    pub scored: bool,
+}
+
+impl UserPrediction {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // user
+        + 8 // prediction_id
+        + 8 // amount
+        + 8 // yes_amount
+        + 8 // no_amount
+        + 1 // reward_claimed
+        + 4 + 8 * MAX_OPTIONS as usize // option_amounts
+        + 4 + 8 * MAX_OPTIONS as usize // shares
+        + 4 // bet_count
+        + 8 // first_bet_at
+        + 8 // last_bet_at
+        + 1; // scored
+
+    /// Adds a parimutuel bet to the running totals. The caller credits the side.
+    pub fn record_bet(
+        &mut self,
+        user: Pubkey,
+        prediction_id: u64,
+        amount: u64,
+        now: i64,
+    ) -> Result<()> {
+        if self.bet_count == 0 {
+            self.user = user;
+            self.prediction_id = prediction_id;
+            self.first_bet_at = now;
+        }
+        self.bet_count = self
+            .bet_count
+            .checked_add(1)
+            .ok_or(PredictionError::Overflow)?;
+        self.last_bet_at = now;
+        self.amount = self
+            .amount
+            .checked_add(amount)
+            .ok_or(PredictionError::Overflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Claims {
+    pub prediction: Pubkey,
+    pub reward_per_lamport: u64,
+    pub total_reward_pool: u64,
+    pub pending_claims: Vec<Claim>,
+}
+
+impl Claims {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // prediction
+        + 8 // reward_per_lamport
+        + 8 // total_reward_pool
+        + 4 + MAX_CLAIMS * Claim::LEN; // pending_claims
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Claim {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub state: ClaimState,
+}
+
+impl Claim {
+    pub const LEN: usize = 32 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PredictionState {
+    #[default]
+    Active,
+    Paused,
+    Resolved,
+    Cancelled,
+    Proposed,
+    Disputed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DisputePhase {
+    Drawing,
+    Voting,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PredictionResult {
+    True,
+    False,
+    #[default]
+    Undefined,
+    Outcome(u8),
+    Scalar(i64),
+    Invalid,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimState {
+    Pending,
+    Approved,
+    Claimed,
+    Rejected,
+}
+
+#[error_code]
+pub enum PredictionError {
+    #[msg("Not authorized to perform this action")]
+    NotAuthorized,
+    #[msg("Prediction is closed")]
+    PredictionClosed,
+    #[msg("Prediction is not active")]
+    PredictionNotActive,
+    #[msg("Prediction has ended")]
+    PredictionEnded,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Prediction already resolved")]
+    PredictionAlreadyResolved,
+    #[msg("Prediction not resolved")]
+    PredictionNotResolved,
+    #[msg("Invalid result")]
+    InvalidResult,
+    #[msg("Invalid duration")]
+    InvalidDuration,
+    #[msg("No tags provided")]
+    NoTags,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Alias too long")]
+    AliasTooLong,
+    #[msg("Claim is not in pending state")]
+    ClaimNotPending,
+    #[msg("User is not a winner")]
+    UserNotWinner,
+    #[msg("Invalid claim index")]
+    InvalidClaimIndex,
+    #[msg("The claims queue is full")]
+    ClaimsFull,
+    #[msg("No approved claim found for the user")]
+    NoApprovedClaim,
+    #[msg("Reward already claimed")]
+    AlreadyClaimed,
+    #[msg("Claim Not winner")]
+    NotWinner,
+    #[msg("Rewards have already been distributed")]
+    RewardsAlreadyDistributed,
+    #[msg("Rewards have not been distributed yet")]
+    RewardsNotDistributed,
+    #[msg("Reward already claimed")]
+    RewardAlreadyClaimed,
+    #[msg("Invalid Prediction")]
+    InvalidPrediction,
+    #[msg("Invalid prediction type")]
+    InvalidPredictionType,
+    #[msg("Invalid number of options")]
+    InvalidOptionsCount,
+    #[msg("Option name too long")]
+    OptionNameTooLong,
+    #[msg("Invalid option")]
+    InvalidOption,
+    #[msg("Invalid scalar range")]
+    InvalidScalarRange,
+    #[msg("Operation not supported in this market mode")]
+    InvalidMarketMode,
+    #[msg("Invalid liquidity parameter")]
+    InvalidLiquidity,
+    #[msg("Price moved beyond the allowed slippage")]
+    SlippageExceeded,
+    #[msg("Insufficient shares")]
+    InsufficientShares,
+    #[msg("Invalid fee")]
+    InvalidFee,
+    #[msg("Insufficient pool liquidity")]
+    InsufficientLiquidity,
+    #[msg("Invalid price")]
+    InvalidPrice,
+    #[msg("Order book is full")]
+    OrderBookFull,
+    #[msg("Order escrow is below the minimum")]
+    OrderTooSmall,
+    #[msg("Order not found")]
+    OrderNotFound,
+    #[msg("Orders do not cross")]
+    OrdersDoNotCross,
+    #[msg("Account does not match the order owner")]
+    InvalidOrderAccount,
+    #[msg("The market's order book is required")]
+    MissingOrderBook,
+    #[msg("Token accounts are required for this market")]
+    MissingTokenAccounts,
+    #[msg("Collateral mint does not match the market")]
+    InvalidCollateralMint,
+    #[msg("Collateral mint uses an unsupported token extension")]
+    UnsupportedMintExtension,
+    #[msg("Only native SOL markets support this operation")]
+    UnsupportedCollateral,
+    #[msg("The cancellation window has closed")]
+    CancelWindowClosed,
+    #[msg("Insufficient stake")]
+    InsufficientStake,
+    #[msg("Prediction has not been cancelled")]
+    PredictionNotCancelled,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Prediction is paused")]
+    PredictionPaused,
+    #[msg("Prediction is not paused")]
+    PredictionNotPaused,
+    #[msg("The marketplace is paused")]
+    MarketPaused,
+    #[msg("Invalid admin")]
+    InvalidAdmin,
+    #[msg("No admin transfer is pending")]
+    NoPendingAdmin,
+    #[msg("Invalid role")]
+    InvalidRole,
+    #[msg("No creator bond to settle")]
+    NoCreatorBond,
+    #[msg("Prediction has not ended yet")]
+    PredictionNotEnded,
+    #[msg("No proposal is pending")]
+    PredictionNotProposed,
+    #[msg("Prediction is not disputed")]
+    PredictionNotDisputed,
+    #[msg("The challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("The challenge window is still open")]
+    ChallengeWindowOpen,
+    #[msg("Invalid price feed")]
+    InvalidPriceFeed,
+    #[msg("Price feed is not trading")]
+    FeedNotTrading,
+    #[msg("Invalid price feed operator")]
+    InvalidFeedOperator,
+    #[msg("Price feed exponent does not match the market")]
+    FeedExponentMismatch,
+    #[msg("Price feed is stale")]
+    FeedStale,
+    #[msg("Price feed confidence interval is too wide")]
+    FeedConfidenceTooWide,
+    #[msg("Market already has stakes")]
+    MarketAlreadyFunded,
+    #[msg("Missing or invalid oracle attestation")]
+    InvalidAttestation,
+    #[msg("Invalid resolver quorum")]
+    InvalidQuorum,
+    #[msg("This market settles through its resolver quorum")]
+    QuorumRequired,
+    #[msg("Resolver has already voted")]
+    AlreadyVoted,
+    #[msg("Invalid panel size")]
+    InvalidPanelSize,
+    #[msg("Juror pool is full")]
+    JurorPoolFull,
+    #[msg("Not enough jurors with free stake")]
+    NotEnoughJurors,
+    #[msg("Not a juror")]
+    NotJuror,
+    #[msg("Seed does not match the commitment")]
+    InvalidSeed,
+    #[msg("The draw slot hash is not available")]
+    SeedSlotUnavailable,
+    #[msg("Vote does not match the commitment")]
+    InvalidCommitment,
+    #[msg("The court is not accepting this action")]
+    InvalidCourtPhase,
+    #[msg("The court is still open")]
+    CourtStillOpen,
+    #[msg("Proposer and disputer accounts are required")]
+    MissingBondAccounts,
+    #[msg("Position has already been scored")]
+    AlreadyScored,
+    #[msg("Invalid user account")]
+    InvalidUserAccount,
+    #[msg("Invalid fee recipient")]
+    InvalidFeeRecipient,
+    #[msg("No creator fee to claim")]
+    NoCreatorFee,
+    #[msg("The LMSR surplus has already been withdrawn")]
+    SurplusAlreadyWithdrawn,
+}
+
+#[event]
+pub struct RewardsDistributedEvent {
+    pub prediction_id: u64,
+    pub total_pool: u64,
+    pub admin_fee: u64,
+    pub reward_pool: u64,
+    pub reward_per_lamport: u64,
+}
+
+#[event]
+pub struct ScalarRewardsDistributedEvent {
+    pub prediction_id: u64,
+    pub value: i64,
+    pub long_reward_per_lamport: u64,
+    pub short_reward_per_lamport: u64,
+}
+
+#[event]
+pub struct PredictionVoidedEvent {
+    pub prediction_id: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct RefundClaimedEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RewardClaimedEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
 #[derive(Accounts)]
-pub struct ClaimReward<'info> {
-    #[account(mut)]
-    pub market_state: Account<'info, MarketState>,
+pub struct InitializeClaims<'info> {
     #[account(
         mut,
-        seeds = [b"prediction", market_state.key().as_ref(), prediction.id.to_le_bytes().as_ref()],
-        bump
+        seeds = [b"prediction", prediction.market.as_ref(), prediction.id.to_le_bytes().as_ref()],
+        bump = prediction.bump,
     )]
     pub prediction: Account<'info, Prediction>,
     #[account(
-        mut,
-        seeds = [b"user_prediction", prediction.key().as_ref(), user.key().as_ref()],
-        bump,
-        constraint = user_prediction.user == user.key() @ PredictionError::NotAuthorized,
-        constraint = user_prediction.prediction_id == prediction.id @ PredictionError::InvalidPrediction,
+        init,
+        payer = admin,
+        space = Claims::LEN,
+        seeds = [b"claims", prediction.key().as_ref()],
+        bump
     )]
-    pub user_prediction: Account<'info, UserPrediction>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct SubmitClaim<'info> {
-    #[account(mut)]
-    pub prediction: Account<'info, Prediction>,
-    #[account(mut)]
     pub claims: Account<'info, Claims>,
-    pub user_prediction: Account<'info, UserPrediction>,
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub admin: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct ApproveClaims<'info> {
-    #[account(mut)]
-    pub market_state: Account<'info, MarketState>,
-    pub prediction: Account<'info, Prediction>,
-    #[account(mut)]
-    pub claims: Account<'info, Claims>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PredictionCreatedEvent {
+    pub prediction_id: u64,
+    pub creator: Pubkey,
+    pub description: String,
+    pub bond: u64,
 }
 
-#[account]
-pub struct MarketState {
-    pub admin: Pubkey,
-    pub next_prediction_id: u64,
+#[event]
+pub struct CreatorBondSettledEvent {
+    pub prediction_id: u64,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub slashed: bool,
 }
 
-#[account]
-pub struct Prediction {
-    pub id: u64,
-    pub state: PredictionState,
-    pub description: String,
-    pub start_time: i64,
-    pub end_time: i64,
-    pub total_votes: u64,
-    pub yes_votes: u64,
-    pub no_votes: u64,
-    pub yes_amount: u64,
-    pub no_amount: u64,
-    pub result: PredictionResult,
-    pub total_amount: u64,
-    pub prediction_type: u8,
-    pub options_count: u8,
-    pub tags: Vec<String>,
-    pub reward_info: Option<RewardInfo>,
-    pub reward_per_lamport: u64,
-    pub rewards_distributed: bool,
+#[event]
+pub struct CreationBondUpdatedEvent {
+    pub bond: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct RewardInfo {
-    pub reward_per_lamport: u64,
-    pub total_reward_pool: u64,
+#[event]
+pub struct UserRegisteredEvent {
+    pub user: Pubkey,
+    pub alias: String,
 }
 
-#[account]
-pub struct UserAccount {
+#[event]
+pub struct AliasUpdatedEvent {
+    pub user: Pubkey,
     pub alias: String,
-    pub rank: u64,
+}
+
+#[event]
+pub struct ReputationUpdatedEvent {
+    pub user: Pubkey,
+    pub prediction_id: u64,
+    pub correct: bool,
     pub reputation: u64,
-    pub total_predictions: u64,
     pub correct_predictions: u64,
+    pub total_predictions: u64,
 }
 
-#[account]
-#[derive(Default)]
-pub struct UserPrediction {
+#[event]
+pub struct LeaderboardUpdatedEvent {
+    pub season: u32,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[event]
+pub struct SeasonStartedEvent {
+    pub season: u32,
+    pub started_at: i64,
+}
+
+#[event]
+pub struct PredictionMadeEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub verdict: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OptionPredictionMadeEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub option_index: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BetCancelledEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+    pub penalty: u64,
+}
+
+#[event]
+pub struct AdminTransferProposedEvent {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferCancelledEvent {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferredEvent {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct RolesUpdatedEvent {
+    pub authority: Pubkey,
+    pub roles: u8,
+}
+
+#[event]
+pub struct FeesWithdrawnEvent {
+    pub treasurer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OracleUpdatedEvent {
+    pub oracle: Pubkey,
+}
+
+#[event]
+pub struct OracleProgramUpdatedEvent {
+    pub oracle_program: Pubkey,
+}
+
+#[event]
+pub struct GuardianUpdatedEvent {
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct GlobalPauseUpdatedEvent {
+    pub paused: bool,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct ProtocolFeeUpdatedEvent {
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+}
+
+#[event]
+pub struct PredictionFeeUpdatedEvent {
+    pub prediction_id: u64,
+    pub fee_bps: u16,
+}
+
+#[event]
+pub struct MaxCreatorFeeUpdatedEvent {
+    pub max_fee_bps: u16,
+}
+
+#[event]
+pub struct CreatorFeeUpdatedEvent {
+    pub prediction_id: u64,
+    pub fee_bps: u16,
+}
+
+#[event]
+pub struct FeeSplitEvent {
+    pub prediction_id: u64,
+    pub creator: Pubkey,
+    pub protocol_fee: u64,
+    pub creator_fee: u64,
+}
+
+#[event]
+pub struct CreatorFeeClaimedEvent {
+    pub prediction_id: u64,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CancelPolicyUpdatedEvent {
+    pub penalty_bps: u16,
+    pub cutoff: i64,
+}
+
+#[event]
+pub struct LmsrInitializedEvent {
+    pub prediction_id: u64,
+    pub liquidity: u64,
+    pub subsidy: u64,
+}
+
+#[event]
+pub struct LmsrSurplusWithdrawnEvent {
+    pub prediction_id: u64,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SharesTradedEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub outcome: u8,
+    pub shares: u64,
+    pub amount: u64,
+    pub is_buy: bool,
+    pub price: u64,
+}
+
+#[event]
+pub struct PoolInitializedEvent {
+    pub prediction_id: u64,
+    pub pool: Pubkey,
+    pub fee_bps: u16,
+}
+
+#[event]
+pub struct LiquidityChangedEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub fees: u64,
+    pub is_deposit: bool,
+}
+
+#[event]
+pub struct OutcomeTradedEvent {
+    pub prediction_id: u64,
+    pub user: Pubkey,
+    pub verdict: bool,
+    pub amount: u64,
+    pub tokens: u64,
+    pub fee: u64,
+    pub is_buy: bool,
+}
+
+#[event]
+pub struct PoolRedeemedEvent {
+    pub prediction_id: u64,
     pub user: Pubkey,
+    pub shares: u64,
+    pub winnings: u64,
+    pub fees: u64,
+}
+
+#[event]
+pub struct OrderPlacedEvent {
+    pub prediction_id: u64,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub verdict: bool,
+    pub price_bps: u16,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct OrderCancelledEvent {
+    pub prediction_id: u64,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub refund: u64,
+}
+
+#[event]
+pub struct OrdersMatchedEvent {
+    pub prediction_id: u64,
+    pub yes_order_id: u64,
+    pub no_order_id: u64,
+    pub quantity: u64,
+    pub yes_price_bps: u16,
+}
+
+#[event]
+pub struct PredictionPausedEvent {
+    pub prediction_id: u64,
+    pub paused_at: i64,
+}
+
+#[event]
+pub struct PredictionResumedEvent {
+    pub prediction_id: u64,
+    pub paused_duration: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct OracleConfigUpdatedEvent {
+    pub proposal_bond: u64,
+    pub challenge_window: i64,
+    pub proposer_reward: u64,
+}
+
+#[event]
+pub struct ResultProposedEvent {
+    pub prediction_id: u64,
+    pub proposer: Pubkey,
+    pub result: PredictionResult,
+    pub bond: u64,
+    pub challenge_deadline: i64,
+}
+
+#[event]
+pub struct ResultDisputedEvent {
+    pub prediction_id: u64,
+    pub disputer: Pubkey,
+    pub bond: u64,
+}
+
+#[event]
+pub struct ResultFinalizedEvent {
+    pub prediction_id: u64,
+    pub result: PredictionResult,
+    pub proposer: Pubkey,
+    pub reward: u64,
+}
+
+#[event]
+pub struct DisputeArbitratedEvent {
     pub prediction_id: u64,
+    pub result: PredictionResult,
+    pub winner: Pubkey,
     pub amount: u64,
-    pub verdict: bool,
-    pub reward_claimed: bool,
 }
 
-#[account]
-pub struct Claims {
-    pub prediction: Pubkey,
-    pub reward_per_lamport: u64,
-    pub total_reward_pool: u64,
-    pub pending_claims: Vec<Claim>,
+#[event]
+pub struct CourtConfigUpdatedEvent {
+    pub juror_stake: u64,
+    pub panel_size: u8,
+    pub court_period: i64,
+    pub court_bond: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct Claim {
-    pub user: Pubkey,
+#[event]
+pub struct JurorStakedEvent {
+    pub juror: Pubkey,
     pub amount: u64,
-    pub state: ClaimState,
+    pub stake: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum PredictionState {
-    Active,
-    Paused,
-    Resolved,
+#[event]
+pub struct JurorWithdrawnEvent {
+    pub juror: Pubkey,
+    pub amount: u64,
+    pub stake: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum PredictionResult {
-    True,
-    False,
-    Undefined,
+#[event]
+pub struct DisputeOpenedEvent {
+    pub prediction_id: u64,
+    pub opener: Pubkey,
+    pub seed_slot: u64,
+    pub draw_deadline: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum ClaimState {
-    Pending,
-    Approved,
-    Claimed,
-    Rejected,
+#[event]
+pub struct PanelDrawnEvent {
+    pub prediction_id: u64,
+    pub jurors: Vec<Pubkey>,
+    pub commit_deadline: i64,
+    pub reveal_deadline: i64,
 }
 
-#[error_code]
-pub enum PredictionError {
-    #[msg("Not authorized to perform this action")]
-    NotAuthorized,
-    #[msg("Prediction is closed")]
-    PredictionClosed,
-    #[msg("Prediction is not active")]
-    PredictionNotActive,
-    #[msg("Prediction has ended")]
-    PredictionEnded,
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Prediction already resolved")]
-    PredictionAlreadyResolved,
-    #[msg("Prediction not resolved")]
-    PredictionNotResolved,
-    #[msg("Invalid result")]
-    InvalidResult,
-    #[msg("Invalid duration")]
-    InvalidDuration,
-    #[msg("No tags provided")]
-    NoTags,
-    #[msg("Arithmetic overflow")]
-    Overflow,
-    #[msg("Alias too long")]
-    AliasTooLong,
-    #[msg("Claim is not in pending state")]
-    ClaimNotPending,
-    #[msg("User is not a winner")]
-    UserNotWinner,
-    #[msg("Invalid claim index")]
-    InvalidClaimIndex,
-    #[msg("No approved claim found for the user")]
-    NoApprovedClaim,
-    #[msg("Reward already claimed")]
-    AlreadyClaimed,
-    #[msg("Claim Not winner")]
-    NotWinner,
-    #[msg("Rewards have already been distributed")]
-    RewardsAlreadyDistributed,
-    #[msg("Rewards have not been distributed yet")]
-    RewardsNotDistributed,
-    #[msg("Reward already claimed")]
-    RewardAlreadyClaimed,
-    #[msg("Invalid Prediction")]
-    InvalidPrediction,
+#[event]
+pub struct JurorVoteCommittedEvent {
+    pub prediction_id: u64,
+    pub juror: Pubkey,
 }
 
 #[event]
-pub struct RewardsDistributedEvent {
+pub struct JurorVoteRevealedEvent {
     pub prediction_id: u64,
-    pub total_pool: u64,
-    pub admin_fee: u64,
-    pub reward_pool: u64,
-    pub reward_per_lamport: u64,
+    pub juror: Pubkey,
+    pub result: PredictionResult,
 }
 
 #[event]
-pub struct RewardClaimedEvent {
+pub struct DisputeSettledEvent {
     pub prediction_id: u64,
-    pub user: Pubkey,
-    pub amount: u64,
+    pub verdict: Option<PredictionResult>,
+    pub applied: bool,
+    pub slashed: u64,
+    pub forfeited_bond: u64,
 }
 
-#[derive(Accounts)]
-pub struct InitializeClaims<'info> {
-    #[account(mut)]
-    pub prediction: Account<'info, Prediction>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 8 + 8 + 4 + (32 + 8 + 1) * 5000, // Increased from 1000 to 5000
-        seeds = [b"claims", prediction.key().as_ref()],
-        bump
-    )]
-    pub claims: Account<'info, Claims>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PriceFeedConfiguredEvent {
+    pub prediction_id: u64,
+    pub price_feed: Pubkey,
+    pub operator: u8,
+    pub threshold: i64,
+    pub exponent: i32,
 }
 
 #[event]
-pub struct PredictionCreatedEvent {
+pub struct FeedResolvedEvent {
     pub prediction_id: u64,
-    pub creator: Pubkey,
-    pub description: String,
+    pub price: i64,
+    pub confidence: u64,
+    pub publish_time: i64,
 }
 
 #[event]
-pub struct PredictionMadeEvent {
+pub struct AttestationResolvedEvent {
     pub prediction_id: u64,
-    pub user: Pubkey,
-    pub verdict: bool,
-    pub amount: u64,
+    pub oracle: Pubkey,
+    pub result: PredictionResult,
+    pub evidence_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResolverQuorumInitializedEvent {
+    pub prediction_id: u64,
+    pub resolvers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct ResolverVoteSubmittedEvent {
+    pub prediction_id: u64,
+    pub resolver: Pubkey,
+    pub result: PredictionResult,
+}
+
+#[event]
+pub struct QuorumDisputedEvent {
+    pub prediction_id: u64,
+    pub resolver: Pubkey,
+    pub result: PredictionResult,
+    pub conflicting_result: PredictionResult,
 }
 
 #[event]
@@ -707,3 +6326,618 @@ pub struct ClaimApprovedEvent {
 // Constants
 pub const INITIAL_REPUTATION: u64 = 100;
 pub const MAX_ALIAS_LENGTH: usize = 20;
+pub const REPUTATION_GAIN: u64 = 10;
+pub const REPUTATION_LOSS: u64 = 5;
+pub const LEADERBOARD_SIZE: usize = 20;
+pub const MIN_RANKED_PREDICTIONS: u64 = 5;
+pub const PREDICTION_TYPE_BINARY: u8 = 0;
+pub const PREDICTION_TYPE_CATEGORICAL: u8 = 1;
+pub const PREDICTION_TYPE_SCALAR: u8 = 2;
+pub const MAX_OPTIONS: u8 = 10;
+pub const MAX_OPTION_NAME_LENGTH: usize = 32;
+pub const REWARD_PRECISION: u64 = 1_000_000;
+pub const MARKET_MODE_PARIMUTUEL: u8 = 0;
+pub const MARKET_MODE_LMSR: u8 = 1;
+pub const MARKET_MODE_CPMM: u8 = 2;
+pub const MARKET_MODE_ORDER_BOOK: u8 = 3;
+pub const BPS_DENOMINATOR: u16 = 10_000;
+pub const MAX_POOL_FEE_BPS: u16 = 1_000;
+pub const DEFAULT_PROTOCOL_FEE_BPS: u16 = 500;
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 1_000;
+pub const DEFAULT_MAX_CREATOR_FEE_BPS: u16 = 5_000;
+pub const FEE_PER_SHARE_PRECISION: u128 = 1_000_000_000_000;
+pub const MAX_OPEN_ORDERS: usize = 32;
+/// Keeps the claims queue within the 10 KiB a single `init` can allocate.
+pub const MAX_CLAIMS: usize = 200;
+pub const MIN_ORDER_ESCROW: u64 = 10_000_000;
+pub const NATIVE_DECIMALS: u8 = 9;
+pub const DEFAULT_CREATION_BOND: u64 = 100_000_000;
+pub const DEFAULT_PROPOSAL_BOND: u64 = 100_000_000;
+pub const DEFAULT_CHALLENGE_WINDOW: i64 = 2 * 60 * 60;
+pub const FEED_OPERATOR_GREATER_THAN: u8 = 0;
+pub const FEED_OPERATOR_LESS_THAN: u8 = 1;
+pub const MAX_RESOLVERS: usize = 7;
+pub const MAX_JURORS: usize = 64;
+pub const MAX_PANEL_SIZE: usize = 9;
+pub const DEFAULT_JUROR_STAKE: u64 = 100_000_000;
+pub const DEFAULT_PANEL_SIZE: u8 = 5;
+pub const DEFAULT_COURT_PERIOD: i64 = 24 * 60 * 60;
+pub const DEFAULT_COURT_BOND: u64 = 100_000_000;
+pub const ROLE_CREATOR: u8 = 1 << 0;
+pub const ROLE_RESOLVER: u8 = 1 << 1;
+pub const ROLE_PAUSER: u8 = 1 << 2;
+pub const ROLE_TREASURER: u8 = 1 << 3;
+pub const ALL_ROLES: u8 = ROLE_CREATOR | ROLE_RESOLVER | ROLE_PAUSER | ROLE_TREASURER;
+
+/// Moves lamports out of an account owned by this program.
+pub fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> Result<()> {
+    if lamports == 0 {
+        return Ok(());
+    }
+    let remaining = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(PredictionError::InsufficientFunds)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(PredictionError::Overflow)?;
+    Ok(())
+}
+
+/// `value * numerator / denominator` without intermediate overflow.
+pub fn mul_div(value: u64, numerator: u64, denominator: u128) -> Result<u64> {
+    let result = (value as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(PredictionError::Overflow)?
+        .checked_div(denominator)
+        .ok_or(PredictionError::Overflow)?;
+    u64::try_from(result).map_err(|_| PredictionError::Overflow.into())
+}
+
+/// Pays lamports out of a market's vault PDA, signing with its seeds.
+pub fn transfer_from_vault<'info>(
+    prediction: &Account<'info, Prediction>,
+    vault: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let prediction_key = prediction.key();
+    anchor_lang::system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: vault.to_account_info(),
+                to,
+            },
+            &[&[b"vault", prediction_key.as_ref(), &[prediction.vault_bump]]],
+        ),
+        amount,
+    )
+}
+
+/// Unwraps and validates the token accounts of a token-denominated market.
+pub fn token_vault_accounts<'a, 'info>(
+    prediction: &Prediction,
+    collateral_mint: &'a Option<InterfaceAccount<'info, Mint>>,
+    token_vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+) -> Result<(
+    &'a InterfaceAccount<'info, Mint>,
+    &'a InterfaceAccount<'info, TokenAccount>,
+    &'a Interface<'info, TokenInterface>,
+)> {
+    let mint = collateral_mint
+        .as_ref()
+        .ok_or(PredictionError::MissingTokenAccounts)?;
+    let token_vault = token_vault
+        .as_ref()
+        .ok_or(PredictionError::MissingTokenAccounts)?;
+    let token_program = token_program
+        .as_ref()
+        .ok_or(PredictionError::MissingTokenAccounts)?;
+    require!(
+        mint.key() == prediction.collateral_mint,
+        PredictionError::InvalidCollateralMint
+    );
+    Ok((mint, token_vault, token_program))
+}
+
+/// Rejects Token-2022 mints whose transfers can deliver less than was sent,
+/// run a hook program, or move tokens out of the vault without its signature.
+/// Stakes are credited at face value, so none of those can back a market.
+pub fn require_supported_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let unsupported = state.get_extension_types()?.into_iter().any(|extension| {
+        matches!(
+            extension,
+            ExtensionType::TransferFeeConfig
+                | ExtensionType::ConfidentialTransferFeeConfig
+                | ExtensionType::TransferHook
+                | ExtensionType::PermanentDelegate
+        )
+    });
+    require!(!unsupported, PredictionError::UnsupportedMintExtension);
+    Ok(())
+}
+
+/// Pays collateral tokens out of a market's token vault, signing as the vault.
+pub fn transfer_from_token_vault<'info>(
+    prediction: &Account<'info, Prediction>,
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let prediction_key = prediction.key();
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: token_vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: token_vault.to_account_info(),
+            },
+            &[&[
+                b"token_vault",
+                prediction_key.as_ref(),
+                &[prediction.token_vault_bump],
+            ]],
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Cancels every open order in `order_book` and returns each escrow to its
+/// owner, who must be among `owners`.
+pub fn refund_open_orders<'info>(
+    prediction_id: u64,
+    order_book: &mut Account<'info, OrderBook>,
+    owners: &[AccountInfo<'info>],
+) -> Result<()> {
+    let order_book_info = order_book.to_account_info();
+    for order in std::mem::take(&mut order_book.orders) {
+        let owner = owners
+            .iter()
+            .find(|info| info.key() == order.owner && info.is_writable)
+            .ok_or(PredictionError::InvalidOrderAccount)?;
+        let refund = order_cost(order.quantity, order.price_bps)?;
+        transfer_lamports(&order_book_info, owner, refund)?;
+
+        emit!(OrderCancelledEvent {
+            prediction_id,
+            order_id: order.id,
+            owner: order.owner,
+            refund,
+        });
+    }
+    Ok(())
+}
+
+/// Lamports needed to buy `quantity` contracts at `price_bps`.
+pub fn order_cost(quantity: u64, price_bps: u16) -> Result<u64> {
+    mul_div(quantity, price_bps as u64, BPS_DENOMINATOR as u128)
+}
+
+pub fn div_ceil(numerator: u128, denominator: u128) -> Result<u64> {
+    require!(denominator > 0, PredictionError::Overflow);
+    let result = numerator
+        .checked_add(denominator - 1)
+        .ok_or(PredictionError::Overflow)?
+        / denominator;
+    u64::try_from(result).map_err(|_| PredictionError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar_market(yes_amount: u64, no_amount: u64, value: i64) -> Prediction {
+        Prediction {
+            state: PredictionState::Resolved,
+            prediction_type: PREDICTION_TYPE_SCALAR,
+            market_mode: MARKET_MODE_PARIMUTUEL,
+            scalar_lower_bound: 0,
+            scalar_upper_bound: 100,
+            yes_amount,
+            no_amount,
+            total_amount: yes_amount + no_amount,
+            result: PredictionResult::Scalar(value),
+            ..Default::default()
+        }
+    }
+
+    fn distribute(prediction: &mut Prediction, reward_pool: u64) {
+        let (long, short) = prediction.scalar_reward_rates(reward_pool).unwrap();
+        prediction.reward_per_lamport = long;
+        prediction.short_reward_per_lamport = short;
+        prediction.rewards_distributed = true;
+    }
+
+    #[test]
+    fn all_long_market_settling_at_the_lower_bound_pays_the_longs() {
+        let mut prediction = scalar_market(1_000, 0, -5);
+        let position = UserPrediction {
+            yes_amount: 1_000,
+            amount: 1_000,
+            ..Default::default()
+        };
+
+        assert!(prediction.is_winner(&position).unwrap());
+        distribute(&mut prediction, 950);
+        assert!(prediction.is_winner(&position).unwrap());
+        assert_eq!(prediction.reward_for(&position).unwrap(), 950);
+    }
+
+    #[test]
+    fn all_short_market_settling_at_the_upper_bound_pays_the_shorts() {
+        let mut prediction = scalar_market(0, 1_000, 250);
+        let position = UserPrediction {
+            no_amount: 1_000,
+            amount: 1_000,
+            ..Default::default()
+        };
+
+        assert!(prediction.is_winner(&position).unwrap());
+        distribute(&mut prediction, 950);
+        assert!(prediction.is_winner(&position).unwrap());
+        assert_eq!(prediction.reward_for(&position).unwrap(), 950);
+    }
+
+    #[test]
+    fn two_sided_market_at_a_bound_pays_only_the_winning_side() {
+        let mut prediction = scalar_market(1_000, 1_000, 100);
+        distribute(&mut prediction, 1_900);
+        let long = UserPrediction {
+            yes_amount: 1_000,
+            ..Default::default()
+        };
+        let short = UserPrediction {
+            no_amount: 1_000,
+            ..Default::default()
+        };
+
+        assert!(prediction.is_winner(&long).unwrap());
+        assert!(!prediction.is_winner(&short).unwrap());
+        assert_eq!(prediction.reward_for(&long).unwrap(), 1_900);
+    }
+
+    #[test]
+    fn reward_rate_for_large_pools_does_not_overflow() {
+        // 20M SOL, far above where `reward_pool * REWARD_PRECISION` wraps u64
+        let reward_pool = 20_000_000 * 1_000_000_000;
+        let rate = mul_div(reward_pool, REWARD_PRECISION, (reward_pool / 2) as u128).unwrap();
+        assert_eq!(rate, 2 * REWARD_PRECISION);
+    }
+
+    #[test]
+    fn voided_parimutuel_refunds_fold_in_cancellation_penalties() {
+        // 1_000 staked in total, then a 200 cancellation left a 40 penalty
+        let prediction = Prediction {
+            state: PredictionState::Cancelled,
+            total_amount: 840,
+            refund_basis: 800,
+            ..Default::default()
+        };
+        let refunds: u64 = [500, 300]
+            .iter()
+            .map(|&amount| {
+                let position = UserPrediction {
+                    amount,
+                    ..Default::default()
+                };
+                prediction.refund_for(&position).unwrap()
+            })
+            .sum();
+        assert_eq!(refunds, 840);
+    }
+
+    #[test]
+    fn voided_lmsr_refunds_are_capped_by_the_book() {
+        let liquidity = 1_000;
+        let subsidy = lmsr::subsidy(liquidity, 2).unwrap();
+        let mut prediction = Prediction {
+            state: PredictionState::Cancelled,
+            market_mode: MARKET_MODE_LMSR,
+            options_count: 2,
+            lmsr_liquidity: liquidity,
+            total_amount: 400,
+            refund_basis: 600,
+            ..Default::default()
+        };
+        let position = UserPrediction {
+            amount: 300,
+            ..Default::default()
+        };
+        // Net spend is covered in full while subsidy and revenue reach it
+        assert_eq!(prediction.refund_for(&position).unwrap(), 300);
+
+        prediction.refund_basis = 400 + subsidy + 1_000;
+        let backing = 400 + subsidy;
+        assert_eq!(
+            prediction.refund_for(&position).unwrap(),
+            300 * backing / prediction.refund_basis
+        );
+    }
+
+    fn pool(yes_reserve: u64, no_reserve: u64) -> Pool {
+        Pool {
+            prediction: Pubkey::default(),
+            yes_reserve,
+            no_reserve,
+            total_shares: yes_reserve.max(no_reserve),
+            fee_bps: 0,
+            fee_per_share: 0,
+            bump: 0,
+            total_cost: 0,
+            collateral: 0,
+        }
+    }
+
+    fn position() -> PoolPosition {
+        PoolPosition {
+            pool: Pubkey::default(),
+            user: Pubkey::default(),
+            yes_tokens: 0,
+            no_tokens: 0,
+            lp_shares: 0,
+            fee_debt: 0,
+            cost: 0,
+        }
+    }
+
+    #[test]
+    fn pool_buy_keeps_the_invariant_and_rounds_for_the_pool() {
+        let mut pool = pool(1_000_000, 1_000_000);
+        let tokens = pool.buy(true, 100_000).unwrap();
+        // 1e12 / 1.1e6 = 909_090.9, rounded up to 909_091 left in the pool
+        assert_eq!(pool.no_reserve, 1_100_000);
+        assert_eq!(pool.yes_reserve, 909_091);
+        assert_eq!(tokens, 1_100_000 - 909_091);
+        assert!(pool.yes_reserve as u128 * pool.no_reserve as u128 >= 1_000_000_000_000);
+    }
+
+    #[test]
+    fn pool_buy_then_sell_never_returns_more_than_was_invested() {
+        for &(yes, no, invested) in &[
+            (1_000_000, 1_000_000, 100_000),
+            (3_000_000, 500_000, 1),
+            (777_777, 123_456, 50_000),
+            (10_000_000_000, 10_000_000_000, 9_999_999_999),
+        ] {
+            let mut pool = pool(yes, no);
+            let tokens = pool.buy(false, invested).unwrap();
+            // Selling back the same collateral costs at least what was bought
+            let mut check = pool.clone();
+            assert!(check.sell(false, invested).unwrap() >= tokens);
+            assert!(pool.yes_reserve as u128 * pool.no_reserve as u128 >= yes as u128 * no as u128);
+        }
+    }
+
+    #[test]
+    fn pool_sell_needs_liquidity_on_the_other_side() {
+        let mut pool = pool(1_000, 1_000);
+        assert!(pool.sell(true, 1_000).is_err());
+        assert!(pool.sell(true, 999).is_ok());
+    }
+
+    #[test]
+    fn pool_fees_are_shared_by_lp_shares() {
+        let mut pool = pool(1_000, 1_000);
+        pool.total_shares = 4_000;
+        pool.collect_fee(100).unwrap();
+        let mut position = PoolPosition {
+            lp_shares: 1_000,
+            ..position()
+        };
+        assert_eq!(pool.settle_fees(&mut position).unwrap(), 25);
+        // Already settled fees are not paid twice
+        assert_eq!(pool.settle_fees(&mut position).unwrap(), 0);
+    }
+
+    #[test]
+    fn voided_pool_refunds_each_position_what_it_paid() {
+        let mut pool = pool(0, 0);
+        let mut lp = position();
+        let mut early = position();
+        let mut late = position();
+        pool.record_deposit(&mut lp, 1_000_000).unwrap();
+        // The same stake buys very different token counts as the price moves
+        pool.record_deposit(&mut early, 100_000).unwrap();
+        pool.record_deposit(&mut late, 250_000).unwrap();
+
+        assert_eq!(pool.refund_for(&lp).unwrap(), 1_000_000);
+        assert_eq!(pool.refund_for(&early).unwrap(), 100_000);
+        assert_eq!(pool.refund_for(&late).unwrap(), 250_000);
+    }
+
+    #[test]
+    fn voided_pool_scales_refunds_after_a_profitable_exit() {
+        let mut pool = pool(0, 0);
+        let mut trader = position();
+        let mut holder = position();
+        pool.record_deposit(&mut trader, 100).unwrap();
+        pool.record_deposit(&mut holder, 300).unwrap();
+        // Selling for 160 recovers the trader's 100 and takes 60 of the
+        // holder's stake with it
+        pool.record_withdrawal(&mut trader, 160).unwrap();
+        assert_eq!(trader.cost, 0);
+        assert_eq!(pool.total_cost, 300);
+        assert_eq!(pool.collateral, 240);
+
+        assert_eq!(pool.refund_for(&trader).unwrap(), 0);
+        assert_eq!(pool.refund_for(&holder).unwrap(), 240);
+    }
+
+    fn token_2022_mint(extensions: &[ExtensionType]) -> Vec<u8> {
+        use spl_token_2022::extension::{
+            permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig,
+            transfer_hook::TransferHook, StateWithExtensionsMut,
+        };
+
+        let len =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+                .unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::TransferFeeConfig => {
+                    state.init_extension::<TransferFeeConfig>(true).unwrap();
+                }
+                ExtensionType::TransferHook => {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }
+                ExtensionType::PermanentDelegate => {
+                    state.init_extension::<PermanentDelegate>(true).unwrap();
+                }
+                _ => unreachable!(),
+            }
+        }
+        state.base.decimals = 6;
+        state.base.is_initialized = true;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    fn check_mint(owner: &Pubkey, mut data: Vec<u8>) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            owner,
+            false,
+            0,
+        );
+        require_supported_mint(&info)
+    }
+
+    #[test]
+    fn plain_mints_are_supported() {
+        assert!(check_mint(&spl_token_2022::ID, token_2022_mint(&[])).is_ok());
+        assert!(check_mint(&anchor_spl::token::ID, vec![0; 82]).is_ok());
+    }
+
+    #[test]
+    fn mints_with_fee_hook_or_delegate_extensions_are_rejected() {
+        for extension in [
+            ExtensionType::TransferFeeConfig,
+            ExtensionType::TransferHook,
+            ExtensionType::PermanentDelegate,
+        ] {
+            assert!(check_mint(&spl_token_2022::ID, token_2022_mint(&[extension])).is_err());
+        }
+    }
+
+    fn fee_market(protocol_fee_bps: u16, creator_fee_bps: u16) -> Prediction {
+        Prediction {
+            state: PredictionState::Resolved,
+            market_mode: MARKET_MODE_PARIMUTUEL,
+            total_amount: 2_000_000,
+            protocol_fee_bps,
+            creator_fee_bps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn creator_fee_comes_out_of_the_protocol_fee() {
+        // 5% of the pool, a fifth of which goes to the creator
+        let (reward_pool, protocol_fee, creator_fee) = fee_market(500, 2_000).fee_split().unwrap();
+        assert_eq!(reward_pool, 1_900_000);
+        assert_eq!(protocol_fee, 80_000);
+        assert_eq!(creator_fee, 20_000);
+
+        let (reward_pool, protocol_fee, creator_fee) = fee_market(500, 0).fee_split().unwrap();
+        assert_eq!(
+            (reward_pool, protocol_fee, creator_fee),
+            (1_900_000, 100_000, 0)
+        );
+    }
+
+    #[test]
+    fn protocol_and_creator_fees_together_stay_under_the_cap() {
+        for creator_fee_bps in [0, DEFAULT_MAX_CREATOR_FEE_BPS, BPS_DENOMINATOR] {
+            let prediction = fee_market(MAX_PROTOCOL_FEE_BPS, creator_fee_bps);
+            let (reward_pool, protocol_fee, creator_fee) = prediction.fee_split().unwrap();
+            assert_eq!(protocol_fee + creator_fee, 200_000);
+            assert_eq!(
+                reward_pool + protocol_fee + creator_fee,
+                prediction.total_amount
+            );
+        }
+
+        let mut lmsr = fee_market(MAX_PROTOCOL_FEE_BPS, BPS_DENOMINATOR);
+        lmsr.market_mode = MARKET_MODE_LMSR;
+        assert_eq!(lmsr.fee_split().unwrap(), (2_000_000, 0, 0));
+    }
+
+    #[test]
+    fn creator_fee_is_claimable_once_after_distribution() {
+        let mut prediction = fee_market(500, 2_000);
+        prediction.creator_fee = 20_000;
+        assert!(prediction.take_creator_fee().is_err());
+
+        prediction.rewards_distributed = true;
+        assert_eq!(prediction.take_creator_fee().unwrap(), 20_000);
+        assert_eq!(prediction.creator_fee, 0);
+        assert!(prediction.take_creator_fee().is_err());
+    }
+
+    #[test]
+    fn leaderboard_orders_by_reputation_and_keeps_only_the_top() {
+        let mut leaderboard = Leaderboard {
+            market: Pubkey::default(),
+            season: 1,
+            season_started_at: 0,
+            updated_at: 0,
+            entries: Vec::new(),
+            bump: 0,
+        };
+        let users: Vec<Pubkey> = (0..=LEADERBOARD_SIZE)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+
+        leaderboard.insert(users[0], 100);
+        leaderboard.insert(users[1], 150);
+        // Ties go behind the profile already there
+        leaderboard.insert(users[2], 100);
+        assert_eq!(leaderboard.rank_of(&users[1]), 1);
+        assert_eq!(leaderboard.rank_of(&users[0]), 2);
+        assert_eq!(leaderboard.rank_of(&users[2]), 3);
+
+        // Re-inserting moves a profile instead of duplicating it
+        leaderboard.insert(users[0], 200);
+        assert_eq!(leaderboard.rank_of(&users[0]), 1);
+        assert_eq!(leaderboard.entries.len(), 3);
+
+        for (index, user) in users.iter().enumerate().skip(3) {
+            leaderboard.insert(*user, 50 - index as u64);
+        }
+        assert_eq!(leaderboard.entries.len(), LEADERBOARD_SIZE);
+        assert_eq!(leaderboard.rank_of(&users[LEADERBOARD_SIZE]), 0);
+
+        leaderboard.remove(&users[0]);
+        assert_eq!(leaderboard.rank_of(&users[0]), 0);
+        assert_eq!(leaderboard.rank_of(&users[1]), 1);
+    }
+}
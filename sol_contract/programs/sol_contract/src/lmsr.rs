@@ -0,0 +1,272 @@
+//! Fixed-point math for the logarithmic market scoring rule.
+//!
+//! All values are `i128`/`u128` fixed-point numbers scaled by `FIXED_ONE`.
+//! Every operation is integer-only so results are identical on every
+//! validator, and every step that can overflow reports `Overflow`.
+
+use crate::PredictionError;
+use anchor_lang::prelude::*;
+
+pub const FIXED_ONE: i128 = 1_000_000_000_000_000_000;
+const LN_2: i128 = 693_147_180_559_945_309;
+/// Below this exponent `exp` rounds to zero at 18 decimals.
+const MIN_EXPONENT: i128 = -42 * FIXED_ONE;
+const SERIES_TERMS: i128 = 30;
+
+/// `e^x` for `x <= 0`, returned in `(0, FIXED_ONE]`.
+fn exp_non_positive(x: i128) -> Result<i128> {
+    require!(x <= 0, PredictionError::Overflow);
+    if x < MIN_EXPONENT {
+        return Ok(0);
+    }
+
+    // e^x = e^-r * 2^-k with r in [0, ln 2)
+    let magnitude = -x;
+    let k = magnitude / LN_2;
+    let r = magnitude - k * LN_2;
+
+    let mut sum = FIXED_ONE;
+    let mut term = FIXED_ONE;
+    for i in 1..=SERIES_TERMS {
+        term = -term.checked_mul(r).ok_or(PredictionError::Overflow)? / FIXED_ONE / i;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+
+    Ok(sum >> k)
+}
+
+/// Natural logarithm of `y >= FIXED_ONE`.
+fn ln_at_least_one(y: i128) -> Result<i128> {
+    require!(y >= FIXED_ONE, PredictionError::Overflow);
+
+    // ln y = k ln 2 + ln z with z in [1, 2)
+    let mut z = y;
+    let mut k = 0;
+    while z >= 2 * FIXED_ONE {
+        z /= 2;
+        k += 1;
+    }
+
+    // ln z = 2 atanh(s) with s = (z - 1) / (z + 1) <= 1/3
+    let s = (z - FIXED_ONE)
+        .checked_mul(FIXED_ONE)
+        .ok_or(PredictionError::Overflow)?
+        / (z + FIXED_ONE);
+    let s_squared = s * s / FIXED_ONE;
+    let mut sum = s;
+    let mut power = s;
+    for j in 1..=SERIES_TERMS {
+        power = power * s_squared / FIXED_ONE;
+        if power == 0 {
+            break;
+        }
+        sum += power / (2 * j + 1);
+    }
+
+    Ok(k * LN_2 + 2 * sum)
+}
+
+/// Returns the largest share count, each `e^((q_i - max) / b)` term and their sum.
+fn normalized_exp_sum(shares: &[u64], liquidity: u64) -> Result<(u64, Vec<i128>, i128)> {
+    require!(liquidity > 0, PredictionError::InvalidLiquidity);
+    let max = shares.iter().copied().max().unwrap_or(0);
+    let mut terms = Vec::with_capacity(shares.len());
+    let mut sum: i128 = 0;
+    for &quantity in shares {
+        let exponent = (quantity as i128 - max as i128)
+            .checked_mul(FIXED_ONE)
+            .ok_or(PredictionError::Overflow)?
+            / liquidity as i128;
+        let term = exp_non_positive(exponent)?;
+        sum = sum.checked_add(term).ok_or(PredictionError::Overflow)?;
+        terms.push(term);
+    }
+    Ok((max, terms, sum))
+}
+
+/// Cost function `C(q) = b ln(sum(e^(q_i / b)))`, scaled by `FIXED_ONE`.
+pub fn cost(shares: &[u64], liquidity: u64) -> Result<u128> {
+    let (max, _, sum) = normalized_exp_sum(shares, liquidity)?;
+    let log_sum = ln_at_least_one(sum)?;
+    let offset = (max as u128)
+        .checked_mul(FIXED_ONE as u128)
+        .ok_or(PredictionError::Overflow)?;
+    let curvature = (liquidity as u128)
+        .checked_mul(log_sum as u128)
+        .ok_or(PredictionError::Overflow)?;
+    offset
+        .checked_add(curvature)
+        .ok_or_else(|| PredictionError::Overflow.into())
+}
+
+/// Instantaneous price of `outcome`, scaled by `precision`.
+pub fn price(shares: &[u64], liquidity: u64, outcome: usize, precision: u64) -> Result<u64> {
+    let (_, terms, sum) = normalized_exp_sum(shares, liquidity)?;
+    let term = *terms.get(outcome).ok_or(PredictionError::InvalidOption)?;
+    let price = term
+        .checked_mul(precision as i128)
+        .ok_or(PredictionError::Overflow)?
+        / sum;
+    Ok(price as u64)
+}
+
+/// Lamports the creator must deposit to cover the worst-case loss `b ln n`.
+pub fn subsidy(liquidity: u64, outcomes: u8) -> Result<u64> {
+    let log_outcomes = ln_at_least_one(outcomes as i128 * FIXED_ONE)?;
+    let subsidy = (liquidity as u128)
+        .checked_mul(log_outcomes as u128)
+        .ok_or(PredictionError::Overflow)?;
+    to_lamports_rounded_up(subsidy)
+}
+
+/// Lamports owed for moving the book from `before` to `after`, rounded up.
+pub fn buy_cost(before: &[u64], after: &[u64], liquidity: u64) -> Result<u64> {
+    let delta = cost(after, liquidity)?
+        .checked_sub(cost(before, liquidity)?)
+        .ok_or(PredictionError::Overflow)?;
+    to_lamports_rounded_up(delta)
+}
+
+/// Lamports returned for moving the book from `before` to `after`, rounded down.
+pub fn sell_proceeds(before: &[u64], after: &[u64], liquidity: u64) -> Result<u64> {
+    let delta = cost(before, liquidity)?
+        .checked_sub(cost(after, liquidity)?)
+        .ok_or(PredictionError::Overflow)?;
+    u64::try_from(delta / FIXED_ONE as u128).map_err(|_| PredictionError::Overflow.into())
+}
+
+fn to_lamports_rounded_up(value: u128) -> Result<u64> {
+    let lamports = value
+        .checked_add(FIXED_ONE as u128 - 1)
+        .ok_or(PredictionError::Overflow)?
+        / FIXED_ONE as u128;
+    u64::try_from(lamports).map_err(|_| PredictionError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Allowed error on fixed-point results: 1e-12.
+    const TOLERANCE: i128 = 1_000_000;
+
+    fn assert_close(actual: i128, expected: i128) {
+        assert!(
+            (actual - expected).abs() <= TOLERANCE,
+            "{actual} is not within {TOLERANCE} of {expected}"
+        );
+    }
+
+    #[test]
+    fn exp_non_positive_matches_known_values() {
+        assert_eq!(exp_non_positive(0).unwrap(), FIXED_ONE);
+        assert_close(
+            exp_non_positive(-FIXED_ONE).unwrap(),
+            367_879_441_171_442_321,
+        );
+        assert_close(exp_non_positive(-LN_2).unwrap(), FIXED_ONE / 2);
+        assert_close(
+            exp_non_positive(-FIXED_ONE / 2).unwrap(),
+            606_530_659_712_633_423,
+        );
+        assert_close(
+            exp_non_positive(-10 * FIXED_ONE).unwrap(),
+            45_399_929_762_484,
+        );
+        assert_eq!(exp_non_positive(MIN_EXPONENT - 1).unwrap(), 0);
+        assert!(exp_non_positive(1).is_err());
+    }
+
+    #[test]
+    fn ln_at_least_one_matches_known_values() {
+        assert_eq!(ln_at_least_one(FIXED_ONE).unwrap(), 0);
+        assert_close(ln_at_least_one(2 * FIXED_ONE).unwrap(), LN_2);
+        assert_close(
+            ln_at_least_one(3 * FIXED_ONE).unwrap(),
+            1_098_612_288_668_109_691,
+        );
+        assert_close(
+            ln_at_least_one(10 * FIXED_ONE).unwrap(),
+            2_302_585_092_994_045_684,
+        );
+        assert_close(
+            ln_at_least_one(2_718_281_828_459_045_235).unwrap(),
+            FIXED_ONE,
+        );
+        assert!(ln_at_least_one(FIXED_ONE - 1).is_err());
+    }
+
+    #[test]
+    fn buying_then_selling_never_pays_out_more_than_it_took_in() {
+        let liquidity = 1_000_000_000;
+        for &(outcomes, outcome, shares) in &[
+            (2, 0, 1),
+            (2, 1, 999),
+            (2, 0, 1_000_000_000),
+            (3, 2, 123_456_789),
+            (5, 4, 7_000_000_000),
+        ] {
+            let before = vec![0; outcomes];
+            let mut after = before.clone();
+            after[outcome] += shares;
+            let cost = buy_cost(&before, &after, liquidity).unwrap();
+            let proceeds = sell_proceeds(&after, &before, liquidity).unwrap();
+            assert!(proceeds <= cost, "{proceeds} > {cost}");
+        }
+    }
+
+    #[test]
+    fn round_trips_on_a_traded_book_never_profit() {
+        let liquidity = 500_000_000;
+        let mut book = vec![0u64; 3];
+        let mut taken = 0u64;
+        let mut paid = 0u64;
+        // Buy in several steps across outcomes, then unwind in another order
+        let trades = [
+            (0, 300_000_000),
+            (1, 50_000_000),
+            (0, 1),
+            (2, 900_000_000),
+            (1, 7),
+        ];
+        for &(outcome, shares) in &trades {
+            let mut next = book.clone();
+            next[outcome] += shares;
+            taken += buy_cost(&book, &next, liquidity).unwrap();
+            book = next;
+        }
+        for &(outcome, shares) in trades.iter().rev() {
+            let mut next = book.clone();
+            next[outcome] -= shares;
+            paid += sell_proceeds(&book, &next, liquidity).unwrap();
+            book = next;
+        }
+        assert!(paid <= taken, "{paid} > {taken}");
+    }
+
+    #[test]
+    fn subsidy_covers_the_worst_case_loss() {
+        let liquidity = 1_000_000_000;
+        for outcomes in 2..=5u8 {
+            let before = vec![0; outcomes as usize];
+            let mut after = before.clone();
+            // Enough shares that the price of outcome 0 is effectively one
+            after[0] = 50 * liquidity;
+            let cost = buy_cost(&before, &after, liquidity).unwrap();
+            let subsidy = subsidy(liquidity, outcomes).unwrap();
+            assert!(cost + subsidy >= after[0]);
+        }
+    }
+
+    #[test]
+    fn prices_sum_to_one() {
+        let book = [400_000_000, 100_000_000, 0];
+        let total: u64 = (0..book.len())
+            .map(|outcome| price(&book, 300_000_000, outcome, 1_000_000).unwrap())
+            .sum();
+        assert!((999_997..=1_000_000).contains(&total));
+    }
+}
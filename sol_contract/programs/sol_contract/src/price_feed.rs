@@ -0,0 +1,62 @@
+//! Reader for Pyth-style (v2) price accounts.
+//!
+//! Only the header fields needed for settlement are parsed, straight from
+//! their byte offsets. The layout says nothing about who publishes the price,
+//! so callers must check the account owner against `MarketState::oracle_program`.
+
+use crate::PredictionError;
+use anchor_lang::prelude::*;
+
+pub const MAGIC: u32 = 0xa1b2_c3d4;
+pub const VERSION: u32 = 2;
+pub const ACCOUNT_TYPE_PRICE: u32 = 3;
+pub const STATUS_TRADING: u32 = 1;
+
+pub const MAGIC_OFFSET: usize = 0;
+pub const VERSION_OFFSET: usize = 4;
+pub const ACCOUNT_TYPE_OFFSET: usize = 8;
+pub const EXPONENT_OFFSET: usize = 20;
+pub const TIMESTAMP_OFFSET: usize = 96;
+pub const PRICE_OFFSET: usize = 208;
+pub const CONFIDENCE_OFFSET: usize = 216;
+pub const STATUS_OFFSET: usize = 224;
+/// Bytes up to and including the aggregate price.
+pub const PRICE_ACCOUNT_LEN: usize = 240;
+
+/// The aggregate price published in a feed account.
+#[derive(Clone, Copy, Debug)]
+pub struct FeedPrice {
+    pub price: i64,
+    pub confidence: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N].try_into().unwrap()
+}
+
+/// Parses a price account, rejecting anything that is not a trading price.
+pub fn load(data: &[u8]) -> Result<FeedPrice> {
+    require!(
+        data.len() >= PRICE_ACCOUNT_LEN,
+        PredictionError::InvalidPriceFeed
+    );
+    require!(
+        u32::from_le_bytes(read(data, MAGIC_OFFSET)) == MAGIC
+            && u32::from_le_bytes(read(data, VERSION_OFFSET)) == VERSION
+            && u32::from_le_bytes(read(data, ACCOUNT_TYPE_OFFSET)) == ACCOUNT_TYPE_PRICE,
+        PredictionError::InvalidPriceFeed
+    );
+    require!(
+        u32::from_le_bytes(read(data, STATUS_OFFSET)) == STATUS_TRADING,
+        PredictionError::FeedNotTrading
+    );
+
+    Ok(FeedPrice {
+        price: i64::from_le_bytes(read(data, PRICE_OFFSET)),
+        confidence: u64::from_le_bytes(read(data, CONFIDENCE_OFFSET)),
+        exponent: i32::from_le_bytes(read(data, EXPONENT_OFFSET)),
+        publish_time: i64::from_le_bytes(read(data, TIMESTAMP_OFFSET)),
+    })
+}
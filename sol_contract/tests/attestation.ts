@@ -0,0 +1,183 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { createHash } from "crypto";
+import { SolContract } from "../target/types/sol_contract";
+
+const {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  Ed25519Program,
+  SYSVAR_CLOCK_PUBKEY,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} = anchor.web3;
+
+// Creator and resolver
+const ROLES = 0b0011;
+
+describe("resolve_with_attestation", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const connection = provider.connection;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const oracle = Keypair.generate();
+  const impostor = Keypair.generate();
+  const evidenceHash = createHash("sha256").update("final tally").digest();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  // The unix timestamp the program compares attestations against
+  const clockTime = async () => {
+    const clock = await connection.getAccountInfo(SYSVAR_CLOCK_PUBKEY);
+    return Number(clock.data.readBigInt64LE(32));
+  };
+
+  // Mirrors the ai-server's choice of attestation timestamp
+  const attestationTimestamp = async () => {
+    const blockTime = await connection.getBlockTime(await connection.getSlot("finalized"));
+    return blockTime ?? Math.floor(Date.now() / 1000);
+  };
+
+  const createMarket = async () => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will turnout top 60%?", new anchor.BN(2), ["politics"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      })
+      .rpc();
+    nextId++;
+    return prediction;
+  };
+
+  // Signs (prediction | result | evidence hash | timestamp) the way the
+  // ai-server does; variant 0 is PredictionResult::True
+  const resolveTrue = (prediction: anchor.web3.PublicKey, timestamp: number, signer = oracle) => {
+    const message = Buffer.concat([
+      prediction.toBuffer(),
+      Buffer.from([0]),
+      evidenceHash,
+      new anchor.BN(timestamp).toArrayLike(Buffer, "le", 8),
+    ]);
+    return program.methods
+      .resolveWithAttestation({ true: {} }, [...evidenceHash], new anchor.BN(timestamp))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPrivateKey({ privateKey: signer.secretKey, message }),
+      ])
+      .rpc();
+  };
+
+  const setGlobalPause = (paused: boolean) =>
+    program.methods
+      .setGlobalPause(paused)
+      .accounts({ marketState: marketState.publicKey, guardian: wallet })
+      .rpc();
+
+  before(async () => {
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .setOracle(oracle.publicKey)
+      .accounts({ marketState: marketState.publicKey, admin: wallet })
+      .rpc();
+  });
+
+  it("resolves with the wall clock when block time is unavailable", async () => {
+    const prediction = await createMarket();
+    await sleep(3000);
+
+    const getBlockTime = connection.getBlockTime;
+    connection.getBlockTime = async () => null;
+    let timestamp: number;
+    try {
+      timestamp = await attestationTimestamp();
+    } finally {
+      connection.getBlockTime = getBlockTime;
+    }
+
+    // The wall clock can run slightly ahead of the cluster's
+    while ((await clockTime()) < timestamp) {
+      await sleep(500);
+    }
+    await resolveTrue(prediction, timestamp);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.deepEqual(account.result, { true: {} });
+    assert.deepEqual(account.state, { resolved: {} });
+  });
+
+  it("rejects an attestation signed by another key", async () => {
+    const prediction = await createMarket();
+    await sleep(3000);
+
+    await expectError(resolveTrue(prediction, await clockTime(), impostor), "InvalidAttestation");
+  });
+
+  it("rejects an attestation while the marketplace is paused", async () => {
+    const prediction = await createMarket();
+    await sleep(3000);
+    const timestamp = await clockTime();
+
+    await setGlobalPause(true);
+    await expectError(resolveTrue(prediction, timestamp), "MarketPaused");
+
+    await setGlobalPause(false);
+    await resolveTrue(prediction, timestamp);
+  });
+});
@@ -0,0 +1,164 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+// Creator and resolver
+const ROLES = 0b0011;
+const BOND = LAMPORTS_PER_SOL / 5;
+
+describe("creation_bond", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const connection = provider.connection;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  // Holds no role, so every market it opens is bonded
+  const creator = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const createMarket = async (bonded: boolean) => {
+    const prediction = predictionPda(nextId);
+    const builder = program.methods
+      .createPrediction("Will the vote pass?", new anchor.BN(3600), ["politics"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: bonded ? creator.publicKey : wallet,
+        role: bonded ? null : rolePda,
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      });
+    await (bonded ? builder.signers([creator]).rpc() : builder.rpc());
+    nextId++;
+    return prediction;
+  };
+
+  type PredictionResult = anchor.IdlTypes<SolContract>["PredictionResult"];
+
+  const resolve = (prediction: anchor.web3.PublicKey, result: PredictionResult) =>
+    program.methods
+      .resolvePrediction(result)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        authority: wallet,
+        role: rolePda,
+        orderBook: null,
+      })
+      .rpc();
+
+  const settleBond = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .settleCreatorBond()
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        creator: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  before(async () => {
+    const signature = await connection.requestAirdrop(creator.publicKey, 10 * LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(signature);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .setCreationBond(new anchor.BN(BOND))
+      .accounts({ marketState: marketState.publicKey, admin: wallet })
+      .rpc();
+  });
+
+  it("lets a wallet without a role create a market by posting the bond", async () => {
+    const prediction = await createMarket(true);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.ok(account.creator.equals(creator.publicKey));
+    assert.equal(account.creatorBond.toNumber(), BOND);
+
+    const rent = await connection.getMinimumBalanceForRentExemption(0);
+    assert.equal(await connection.getBalance(vaultPda(prediction)), rent + BOND);
+  });
+
+  it("charges role holders no bond", async () => {
+    const prediction = await createMarket(false);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.equal(account.creatorBond.toNumber(), 0);
+  });
+
+  it("refunds the bond once the market resolves", async () => {
+    const prediction = await createMarket(true);
+    await expectError(settleBond(prediction), "PredictionNotResolved");
+
+    await resolve(prediction, { true: {} });
+    const before = await connection.getBalance(creator.publicKey);
+    await settleBond(prediction);
+
+    assert.equal(await connection.getBalance(creator.publicKey), before + BOND);
+    const account = await program.account.prediction.fetch(prediction);
+    assert.equal(account.creatorBond.toNumber(), 0);
+
+    await expectError(settleBond(prediction), "NoCreatorBond");
+  });
+
+  it("forfeits the bond to the treasury when the market is voided", async () => {
+    const prediction = await createMarket(true);
+    await resolve(prediction, { invalid: {} });
+
+    const creatorBefore = await connection.getBalance(creator.publicKey);
+    const treasuryBefore = await connection.getBalance(marketState.publicKey);
+    await settleBond(prediction);
+
+    assert.equal(await connection.getBalance(creator.publicKey), creatorBefore);
+    assert.equal(await connection.getBalance(marketState.publicKey), treasuryBefore + BOND);
+  });
+});
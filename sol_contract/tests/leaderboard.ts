@@ -0,0 +1,354 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+// Creator, resolver, pauser and treasurer
+const ALL_ROLES = 0b1111;
+// Matches MIN_RANKED_PREDICTIONS
+const MIN_RANKED_PREDICTIONS = 5;
+
+describe("leaderboard", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const alice = Keypair.generate();
+  const bob = Keypair.generate();
+  // Registered but never scored, so never ranked
+  const carol = Keypair.generate();
+  // A marketplace anyone can stand up, whose markets must not score profiles here
+  const rogueMarketState = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const userPredictionPda = (prediction: anchor.web3.PublicKey, user: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_prediction"), prediction.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const userAccountPda = (user: anchor.web3.PublicKey, market = marketState.publicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user"), market.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const leaderboardPda = PublicKey.findProgramAddressSync(
+    [Buffer.from("leaderboard"), marketState.publicKey.toBuffer()],
+    program.programId
+  )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  const tokenAccounts = {
+    collateralMint: null,
+    tokenVault: null,
+    tokenProgram: null,
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const airdrop = async (key: anchor.web3.PublicKey) => {
+    const signature = await provider.connection.requestAirdrop(key, 10 * LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+  };
+
+  const registerUser = (user: anchor.web3.Keypair, alias: string, market = marketState.publicKey) =>
+    program.methods
+      .registerUser(alias)
+      .accounts({
+        marketState: market,
+        userAccount: userAccountPda(user.publicKey, market),
+        user: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+
+  const predict = (
+    prediction: anchor.web3.PublicKey,
+    user: anchor.web3.Keypair,
+    verdict: boolean,
+    userAccount = userAccountPda(user.publicKey)
+  ) =>
+    program.methods
+      .predict(verdict, new anchor.BN(LAMPORTS_PER_SOL / 10))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        user: user.publicKey,
+        userPrediction: userPredictionPda(prediction, user.publicKey),
+        systemProgram: SystemProgram.programId,
+        userTokenAccount: null,
+        userAccount,
+        ...tokenAccounts,
+      })
+      .signers([user])
+      .rpc();
+
+  const updateReputation = (prediction: anchor.web3.PublicKey, user: anchor.web3.PublicKey) =>
+    program.methods
+      .updateReputation()
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        userPrediction: userPredictionPda(prediction, user),
+        userAccount: userAccountPda(user),
+      })
+      .rpc();
+
+  const createMarket = async () => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will the index close higher?", new anchor.BN(3600), ["markets"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        ...tokenAccounts,
+      })
+      .rpc();
+    nextId++;
+    return prediction;
+  };
+
+  // A market alice calls right and bob calls wrong, scored for both
+  const playMarket = async (doubleBet = false) => {
+    const prediction = await createMarket();
+    await predict(prediction, alice, true);
+    if (doubleBet) {
+      await predict(prediction, alice, true);
+    }
+    await predict(prediction, bob, false);
+
+    await program.methods
+      .resolvePrediction({ true: {} })
+      .accounts({ marketState: marketState.publicKey, prediction, authority: wallet, role: rolePda, orderBook: null })
+      .rpc();
+    await program.methods
+      .distributeRewards()
+      .accounts({
+        prediction,
+        vault: vaultPda(prediction),
+        marketState: marketState.publicKey,
+        feeRecipient: marketState.publicKey,
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        feeTokenAccount: null,
+        ...tokenAccounts,
+      })
+      .rpc();
+
+    await updateReputation(prediction, alice.publicKey);
+    await updateReputation(prediction, bob.publicKey);
+    return prediction;
+  };
+
+  const updateLeaderboard = (users: anchor.web3.PublicKey[], isWritable = true) =>
+    program.methods
+      .updateLeaderboard()
+      .accounts({ marketState: marketState.publicKey, leaderboard: leaderboardPda })
+      .remainingAccounts(
+        users.map((user) => ({ pubkey: userAccountPda(user), isSigner: false, isWritable }))
+      )
+      .rpc();
+
+  before(async () => {
+    await airdrop(alice.publicKey);
+    await airdrop(bob.publicKey);
+    await airdrop(carol.publicKey);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ALL_ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .initializeLeaderboard()
+      .accounts({
+        marketState: marketState.publicKey,
+        leaderboard: leaderboardPda,
+        admin: wallet,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  describe("profiles", () => {
+    it("rejects an alias that is too long", async () => {
+      await expectError(registerUser(alice, "a".repeat(64)), "AliasTooLong");
+    });
+
+    it("registers profiles while the marketplace is open", async () => {
+      await program.methods
+        .setGlobalPause(true)
+        .accounts({ marketState: marketState.publicKey, guardian: wallet })
+        .rpc();
+      await expectError(registerUser(alice, "alice"), "MarketPaused");
+      await program.methods
+        .setGlobalPause(false)
+        .accounts({ marketState: marketState.publicKey, guardian: wallet })
+        .rpc();
+
+      await registerUser(alice, "alice");
+      await registerUser(bob, "bob");
+      await registerUser(carol, "carol");
+
+      const profile = await program.account.userAccount.fetch(userAccountPda(alice.publicKey));
+      assert.equal(profile.alias, "alice");
+      assert.equal(profile.reputation.toNumber(), 100);
+      assert.equal(profile.totalPredictions.toNumber(), 0);
+    });
+
+    it("counts each market once and scores settled positions", async () => {
+      await playMarket(true);
+      for (let played = 1; played < MIN_RANKED_PREDICTIONS; played++) {
+        await playMarket();
+      }
+
+      const winner = await program.account.userAccount.fetch(userAccountPda(alice.publicKey));
+      assert.equal(winner.totalPredictions.toNumber(), MIN_RANKED_PREDICTIONS);
+      assert.equal(winner.scoredPredictions.toNumber(), MIN_RANKED_PREDICTIONS);
+      assert.equal(winner.correctPredictions.toNumber(), MIN_RANKED_PREDICTIONS);
+      assert.equal(winner.reputation.toNumber(), 100 + 10 * MIN_RANKED_PREDICTIONS);
+
+      const loser = await program.account.userAccount.fetch(userAccountPda(bob.publicKey));
+      assert.equal(loser.scoredPredictions.toNumber(), MIN_RANKED_PREDICTIONS);
+      assert.equal(loser.correctPredictions.toNumber(), 0);
+      assert.equal(loser.reputation.toNumber(), 100 - 5 * MIN_RANKED_PREDICTIONS);
+    });
+
+    it("rejects scoring a position twice", async () => {
+      await expectError(updateReputation(predictionPda(0), alice.publicKey), "AlreadyScored");
+    });
+
+    it("keeps profiles separate per marketplace", async () => {
+      await program.methods
+        .initialize()
+        .accounts({ marketState: rogueMarketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+        .signers([rogueMarketState])
+        .rpc();
+      await registerUser(alice, "alice", rogueMarketState.publicKey);
+
+      const rogue = await program.account.userAccount.fetch(userAccountPda(alice.publicKey, rogueMarketState.publicKey));
+      assert.ok(rogue.market.equals(rogueMarketState.publicKey));
+      assert.equal(rogue.reputation.toNumber(), 100);
+
+      // A profile from another marketplace cannot be counted by this one
+      const prediction = await createMarket();
+      await expectError(
+        predict(prediction, alice, true, userAccountPda(alice.publicKey, rogueMarketState.publicKey)),
+        "ConstraintSeeds"
+      );
+    });
+  });
+
+  describe("update_leaderboard", () => {
+    it("rejects a crank without candidates", async () => {
+      await expectError(updateLeaderboard([]), "InvalidUserAccount");
+    });
+
+    it("rejects profiles from another marketplace", async () => {
+      await expectError(
+        program.methods
+          .updateLeaderboard()
+          .accounts({ marketState: marketState.publicKey, leaderboard: leaderboardPda })
+          .remainingAccounts([
+            { pubkey: userAccountPda(alice.publicKey, rogueMarketState.publicKey), isSigner: false, isWritable: true },
+          ])
+          .rpc(),
+        "InvalidUserAccount"
+      );
+    });
+
+    it("rejects read-only candidates, whose rank could not be written", async () => {
+      await expectError(updateLeaderboard([alice.publicKey], false), "InvalidUserAccount");
+    });
+
+    it("ranks profiles by reputation and writes the ranks back", async () => {
+      await updateLeaderboard([bob.publicKey, carol.publicKey, alice.publicKey]);
+
+      const leaderboard = await program.account.leaderboard.fetch(leaderboardPda);
+      assert.deepEqual(
+        leaderboard.entries.map((entry) => entry.user.toBase58()),
+        [alice.publicKey.toBase58(), bob.publicKey.toBase58()]
+      );
+
+      const ranks = await Promise.all(
+        [alice, bob, carol].map((user) => program.account.userAccount.fetch(userAccountPda(user.publicKey)))
+      );
+      assert.deepEqual(
+        ranks.map((profile) => profile.rank.toNumber()),
+        [1, 2, 0]
+      );
+      assert.equal(ranks[0].rankSeason, 1);
+    });
+
+    it("rejects a season reset from anyone but the admin", async () => {
+      await expectError(
+        program.methods
+          .resetSeason()
+          .accounts({ marketState: marketState.publicKey, leaderboard: leaderboardPda, admin: alice.publicKey })
+          .signers([alice])
+          .rpc(),
+        "NotAuthorized"
+      );
+    });
+
+    it("clears the board for a new season", async () => {
+      await program.methods
+        .resetSeason()
+        .accounts({ marketState: marketState.publicKey, leaderboard: leaderboardPda, admin: wallet })
+        .rpc();
+
+      const leaderboard = await program.account.leaderboard.fetch(leaderboardPda);
+      assert.equal(leaderboard.season, 2);
+      assert.equal(leaderboard.entries.length, 0);
+
+      // Profiles keep last season's rank, tagged with that season
+      const profile = await program.account.userAccount.fetch(userAccountPda(alice.publicKey));
+      assert.equal(profile.rank.toNumber(), 1);
+      assert.equal(profile.rankSeason, 1);
+    });
+  });
+});
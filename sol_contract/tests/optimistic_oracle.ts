@@ -0,0 +1,243 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+// Creator and resolver
+const ROLES = 0b0011;
+const BOND = LAMPORTS_PER_SOL / 10;
+const REWARD = LAMPORTS_PER_SOL / 100;
+const CHALLENGE_WINDOW = 3;
+
+describe("optimistic_oracle", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const connection = provider.connection;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const proposer = Keypair.generate();
+  const disputer = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  type PredictionResult = anchor.IdlTypes<SolContract>["PredictionResult"];
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const airdrop = async (key: anchor.web3.PublicKey) => {
+    const signature = await connection.requestAirdrop(key, 10 * LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(signature);
+  };
+
+  const createMarket = async (duration: number) => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will the merger close?", new anchor.BN(duration), ["finance"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      })
+      .rpc();
+    nextId++;
+    return prediction;
+  };
+
+  const propose = (prediction: anchor.web3.PublicKey, result: PredictionResult) =>
+    program.methods
+      .proposeResult(result)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        proposer: proposer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([proposer])
+      .rpc();
+
+  const dispute = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .disputeResult()
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        disputer: disputer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([disputer])
+      .rpc();
+
+  const finalize = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .finalizeResult()
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        proposer: proposer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  const arbitrate = (prediction: anchor.web3.PublicKey, result: PredictionResult) =>
+    program.methods
+      .arbitrate(result)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        proposer: proposer.publicKey,
+        disputer: disputer.publicKey,
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  let open: anchor.web3.PublicKey;
+  let undisputed: anchor.web3.PublicKey;
+  let overturned: anchor.web3.PublicKey;
+  let upheld: anchor.web3.PublicKey;
+
+  before(async () => {
+    await airdrop(proposer.publicKey);
+    await airdrop(disputer.publicKey);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .setOracleConfig(new anchor.BN(BOND), new anchor.BN(CHALLENGE_WINDOW), new anchor.BN(REWARD))
+      .accounts({ marketState: marketState.publicKey, admin: wallet })
+      .rpc();
+
+    // Fund the treasury that pays the proposer reward
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({ fromPubkey: wallet, toPubkey: marketState.publicKey, lamports: LAMPORTS_PER_SOL })
+      )
+    );
+
+    open = await createMarket(3600);
+    undisputed = await createMarket(2);
+    overturned = await createMarket(2);
+    upheld = await createMarket(2);
+    await sleep(3000);
+  });
+
+  it("rejects proposals before the market ends", async () => {
+    await expectError(propose(open, { true: {} }), "PredictionNotEnded");
+  });
+
+  it("holds the proposal bond in the vault", async () => {
+    const before = await connection.getBalance(vaultPda(undisputed));
+    await propose(undisputed, { true: {} });
+
+    assert.equal(await connection.getBalance(vaultPda(undisputed)), before + BOND);
+    const account = await program.account.prediction.fetch(undisputed);
+    assert.deepEqual(account.state, { proposed: {} });
+    assert.deepEqual(account.proposedResult, { true: {} });
+    assert.ok(account.proposer.equals(proposer.publicKey));
+    assert.equal(account.resolutionBond.toNumber(), BOND);
+  });
+
+  it("rejects finalizing while the challenge window is open", async () => {
+    await expectError(finalize(undisputed), "ChallengeWindowOpen");
+  });
+
+  it("rejects disputes once the challenge window closes", async () => {
+    await sleep((CHALLENGE_WINDOW + 1) * 1000);
+    await expectError(dispute(undisputed), "ChallengeWindowClosed");
+  });
+
+  it("finalizes an undisputed proposal and pays the bond back with the reward", async () => {
+    const before = await connection.getBalance(proposer.publicKey);
+    await finalize(undisputed);
+
+    assert.equal(await connection.getBalance(proposer.publicKey), before + BOND + REWARD);
+    const account = await program.account.prediction.fetch(undisputed);
+    assert.deepEqual(account.state, { resolved: {} });
+    assert.deepEqual(account.result, { true: {} });
+  });
+
+  it("escalates a disputed proposal to arbitration", async () => {
+    await propose(overturned, { false: {} });
+    const before = await connection.getBalance(vaultPda(overturned));
+    await dispute(overturned);
+
+    assert.equal(await connection.getBalance(vaultPda(overturned)), before + BOND);
+    const account = await program.account.prediction.fetch(overturned);
+    assert.deepEqual(account.state, { disputed: {} });
+    assert.ok(account.disputer.equals(disputer.publicKey));
+
+    await expectError(finalize(overturned), "PredictionNotProposed");
+  });
+
+  it("pays both bonds to the disputer when the proposal is overturned", async () => {
+    const before = await connection.getBalance(disputer.publicKey);
+    await arbitrate(overturned, { true: {} });
+
+    assert.equal(await connection.getBalance(disputer.publicKey), before + 2 * BOND);
+    const account = await program.account.prediction.fetch(overturned);
+    assert.deepEqual(account.result, { true: {} });
+  });
+
+  it("pays both bonds to the proposer when the proposal is upheld", async () => {
+    await propose(upheld, { false: {} });
+    await dispute(upheld);
+
+    const before = await connection.getBalance(proposer.publicKey);
+    await arbitrate(upheld, { false: {} });
+
+    assert.equal(await connection.getBalance(proposer.publicKey), before + 2 * BOND);
+    await expectError(arbitrate(upheld, { false: {} }), "PredictionNotDisputed");
+  });
+});
@@ -0,0 +1,455 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+// Creator, resolver, pauser and treasurer
+const ALL_ROLES = 0b1111;
+
+describe("pause", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const bettor = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const userPredictionPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_prediction"), prediction.toBuffer(), bettor.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  const tokenAccounts = {
+    collateralMint: null,
+    tokenVault: null,
+    tokenProgram: null,
+  };
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const createMarket = async () => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will the launch slip?", new anchor.BN(3600), ["space"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        ...tokenAccounts,
+      })
+      .rpc();
+    // Only a successful creation advances the market's id counter
+    nextId++;
+    return prediction;
+  };
+
+  const createLmsrMarket = async () => {
+    const prediction = await createMarket();
+    await program.methods
+      .initializeLmsr(new anchor.BN(LAMPORTS_PER_SOL / 10))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return prediction;
+  };
+
+  const predict = (prediction: anchor.web3.PublicKey, amount = LAMPORTS_PER_SOL / 10) =>
+    program.methods
+      .predict(true, new anchor.BN(amount))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        user: bettor.publicKey,
+        userPrediction: userPredictionPda(prediction),
+        systemProgram: SystemProgram.programId,
+        userTokenAccount: null,
+        userAccount: null,
+        ...tokenAccounts,
+      })
+      .signers([bettor])
+      .rpc();
+
+  const cancelBet = (prediction: anchor.web3.PublicKey, amount = LAMPORTS_PER_SOL / 20) =>
+    program.methods
+      .cancelBet(0, new anchor.BN(amount))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        userPrediction: userPredictionPda(prediction),
+        user: bettor.publicKey,
+        systemProgram: SystemProgram.programId,
+        userTokenAccount: null,
+        ...tokenAccounts,
+      })
+      .signers([bettor])
+      .rpc();
+
+  const buyShares = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .buyShares(0, new anchor.BN(1_000_000), new anchor.BN(LAMPORTS_PER_SOL))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        user: bettor.publicKey,
+        userPrediction: userPredictionPda(prediction),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([bettor])
+      .rpc();
+
+  const pausePrediction = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .pausePrediction()
+      .accounts({ marketState: marketState.publicKey, prediction, authority: wallet, role: rolePda })
+      .rpc();
+
+  const resumePrediction = (prediction: anchor.web3.PublicKey, extendEndTime: boolean) =>
+    program.methods
+      .resumePrediction(extendEndTime)
+      .accounts({ marketState: marketState.publicKey, prediction, authority: wallet, role: rolePda })
+      .rpc();
+
+  before(async () => {
+    const signature = await provider.connection.requestAirdrop(bettor.publicKey, 10 * LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+
+    await program.methods
+      .setRoles(wallet, ALL_ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+  });
+
+  describe("pause_prediction", () => {
+    let parimutuel: anchor.web3.PublicKey;
+    let lmsr: anchor.web3.PublicKey;
+
+    before(async () => {
+      parimutuel = await createMarket();
+      lmsr = await createLmsrMarket();
+      await predict(parimutuel);
+    });
+
+    it("rejects bets, cancellations and trades while paused", async () => {
+      await pausePrediction(parimutuel);
+      await pausePrediction(lmsr);
+
+      const account = await program.account.prediction.fetch(parimutuel);
+      assert.deepEqual(account.state, { paused: {} });
+
+      await expectError(predict(parimutuel), "PredictionPaused");
+      await expectError(cancelBet(parimutuel), "PredictionPaused");
+      await expectError(buyShares(lmsr), "PredictionPaused");
+    });
+
+    it("rejects pausing a market twice", async () => {
+      await expectError(pausePrediction(parimutuel), "PredictionPaused");
+    });
+
+    it("accepts bets, cancellations and trades again after resuming", async () => {
+      await resumePrediction(parimutuel, false);
+      await resumePrediction(lmsr, false);
+
+      await predict(parimutuel);
+      await cancelBet(parimutuel);
+      await buyShares(lmsr);
+
+      const account = await program.account.prediction.fetch(parimutuel);
+      assert.deepEqual(account.state, { active: {} });
+      assert.equal(account.yesAmount.toNumber(), (2 * LAMPORTS_PER_SOL) / 10 - LAMPORTS_PER_SOL / 20);
+    });
+
+    it("rejects resuming a market that is not paused", async () => {
+      await expectError(resumePrediction(parimutuel, false), "PredictionNotPaused");
+    });
+
+    it("extends the deadlines by the paused time when asked", async () => {
+      const before = await program.account.prediction.fetch(parimutuel);
+      await pausePrediction(parimutuel);
+      await sleep(3000);
+      await resumePrediction(parimutuel, true);
+
+      const after = await program.account.prediction.fetch(parimutuel);
+      const extension = after.endTime.sub(before.endTime).toNumber();
+      assert.isAtLeast(extension, 2);
+      assert.equal(after.cancelDeadline.sub(before.cancelDeadline).toNumber(), extension);
+    });
+  });
+
+  describe("set_global_pause", () => {
+    let parimutuel: anchor.web3.PublicKey;
+    let lmsr: anchor.web3.PublicKey;
+    let cpmm: anchor.web3.PublicKey;
+    let orderBookMarket: anchor.web3.PublicKey;
+    // Takes the other side of the bettor's order
+    const counterparty = Keypair.generate();
+
+    const poolPda = (prediction: anchor.web3.PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("pool"), prediction.toBuffer()],
+        program.programId
+      )[0];
+
+    const orderBookPda = (prediction: anchor.web3.PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("order_book"), prediction.toBuffer()],
+        program.programId
+      )[0];
+
+    const poolPositionPda = (user: anchor.web3.PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("pool_position"), poolPda(cpmm).toBuffer(), user.toBuffer()],
+        program.programId
+      )[0];
+
+    const orderPositionPda = (user: anchor.web3.PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("order_position"), orderBookMarket.toBuffer(), user.toBuffer()],
+        program.programId
+      )[0];
+
+    const jurorPoolPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("jurors"), marketState.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+    const setGlobalPause = (paused: boolean, guardian: anchor.web3.Keypair | null = null) => {
+      const builder = program.methods
+        .setGlobalPause(paused)
+        .accounts({
+          marketState: marketState.publicKey,
+          guardian: guardian ? guardian.publicKey : wallet,
+        });
+      return guardian ? builder.signers([guardian]).rpc() : builder.rpc();
+    };
+
+    const addLiquidity = () =>
+      program.methods
+        .addLiquidity(new anchor.BN(LAMPORTS_PER_SOL / 10))
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: cpmm,
+          vault: vaultPda(cpmm),
+          pool: poolPda(cpmm),
+          position: poolPositionPda(bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+
+    const placeOrder = (user = bettor, verdict = true) =>
+      program.methods
+        .placeOrder(verdict, 5000, new anchor.BN(100_000_000))
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: orderBookMarket,
+          orderBook: orderBookPda(orderBookMarket),
+          position: orderPositionPda(user.publicKey),
+          user: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+
+    const voidMarket = (prediction: anchor.web3.PublicKey, orderBook: anchor.web3.PublicKey | null) =>
+      program.methods
+        .resolvePrediction({ invalid: {} })
+        .accounts({ marketState: marketState.publicKey, prediction, authority: wallet, role: rolePda, orderBook })
+        .rpc();
+
+    const registerJuror = () =>
+      program.methods
+        .registerJuror(new anchor.BN(LAMPORTS_PER_SOL / 10))
+        .accounts({
+          marketState: marketState.publicKey,
+          jurorPool: jurorPoolPda,
+          juror: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+
+    before(async () => {
+      parimutuel = await createMarket();
+      lmsr = await createLmsrMarket();
+
+      cpmm = await createMarket();
+      await program.methods
+        .initializePool(30)
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: cpmm,
+          pool: poolPda(cpmm),
+          authority: wallet,
+          role: rolePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      orderBookMarket = await createMarket();
+      await program.methods
+        .initializeOrderBook()
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: orderBookMarket,
+          orderBook: orderBookPda(orderBookMarket),
+          authority: wallet,
+          role: rolePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await predict(parimutuel);
+    });
+
+    it("rejects a pause from anyone but the guardian", async () => {
+      await expectError(setGlobalPause(true, bettor), "NotAuthorized");
+    });
+
+    it("halts every market family while paused", async () => {
+      await setGlobalPause(true);
+
+      const state = await program.account.marketState.fetch(marketState.publicKey);
+      assert.isTrue(state.paused);
+
+      await expectError(createMarket(), "MarketPaused");
+      await expectError(predict(parimutuel), "MarketPaused");
+      await expectError(cancelBet(parimutuel), "MarketPaused");
+      await expectError(buyShares(lmsr), "MarketPaused");
+      await expectError(addLiquidity(), "MarketPaused");
+      await expectError(placeOrder(), "MarketPaused");
+      await expectError(registerJuror(), "MarketPaused");
+    });
+
+    it("reopens every market family once unpaused", async () => {
+      await setGlobalPause(false);
+
+      await createMarket();
+      await predict(parimutuel);
+      await cancelBet(parimutuel);
+      await buyShares(lmsr);
+      await addLiquidity();
+      await placeOrder();
+      await registerJuror();
+
+      const state = await program.account.marketState.fetch(marketState.publicKey);
+      assert.isFalse(state.paused);
+    });
+
+    it("keeps refunds for voided pool and order positions open while paused", async () => {
+      const signature = await provider.connection.requestAirdrop(counterparty.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(signature);
+
+      // Fill the bettor's YES order so both sides hold contracts
+      await placeOrder(counterparty, false);
+      const [yesOrder, noOrder] = (await program.account.orderBook.fetch(orderBookPda(orderBookMarket))).orders;
+      await program.methods
+        .matchOrders(yesOrder.id, noOrder.id)
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: orderBookMarket,
+          vault: vaultPda(orderBookMarket),
+          orderBook: orderBookPda(orderBookMarket),
+          yesPosition: orderPositionPda(bettor.publicKey),
+          noPosition: orderPositionPda(counterparty.publicKey),
+          yesOwner: bettor.publicKey,
+          noOwner: counterparty.publicKey,
+        })
+        .rpc();
+
+      await voidMarket(cpmm, null);
+      await voidMarket(orderBookMarket, orderBookPda(orderBookMarket));
+      await setGlobalPause(true);
+
+      const poolRefund = (await program.account.poolPosition.fetch(poolPositionPda(bettor.publicKey))).cost.toNumber();
+      const orderRefund = (await program.account.orderPosition.fetch(orderPositionPda(bettor.publicKey))).cost.toNumber();
+      const before = await provider.connection.getBalance(bettor.publicKey);
+
+      await program.methods
+        .redeemPoolPosition()
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: cpmm,
+          vault: vaultPda(cpmm),
+          pool: poolPda(cpmm),
+          position: poolPositionPda(bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+      await program.methods
+        .redeemOrderPosition()
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction: orderBookMarket,
+          vault: vaultPda(orderBookMarket),
+          position: orderPositionPda(bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+
+      assert.isAbove(poolRefund, 0);
+      assert.isAbove(orderRefund, 0);
+      assert.equal(await provider.connection.getBalance(bettor.publicKey), before + poolRefund + orderRefund);
+
+      await setGlobalPause(false);
+    });
+  });
+});
@@ -0,0 +1,264 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+import { MockPriceFeed } from "../target/types/mock_price_feed";
+
+const { PublicKey, Keypair, SystemProgram } = anchor.web3;
+
+// Mirrors `price_feed::PRICE_ACCOUNT_LEN` and `price_feed::STATUS_TRADING`
+const PRICE_ACCOUNT_LEN = 240;
+const STATUS_TRADING = 1;
+const EXPONENT = -8;
+const FEED_OPERATOR_GREATER_THAN = 0;
+const ROLE_CREATOR = 0b0001;
+const ROLE_RESOLVER = 0b0010;
+
+describe("resolve_from_feed", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const mockFeed = anchor.workspace.MockPriceFeed as Program<MockPriceFeed>;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const creatorOnly = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const roleFor = (authority: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), marketState.publicKey.toBuffer(), authority.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = roleFor(wallet);
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const chainTime = async () => {
+    const slot = await provider.connection.getSlot();
+    return provider.connection.getBlockTime(slot);
+  };
+
+  const createFeed = async (owner = mockFeed.programId) => {
+    const feed = Keypair.generate();
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(PRICE_ACCOUNT_LEN);
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: wallet,
+        newAccountPubkey: feed.publicKey,
+        lamports,
+        space: PRICE_ACCOUNT_LEN,
+        programId: owner,
+      })
+    );
+    await provider.sendAndConfirm(tx, [feed]);
+    return feed.publicKey;
+  };
+
+  const publish = (feed: anchor.web3.PublicKey, price: number, confidence: number, publishTime: number) =>
+    mockFeed.methods
+      .setPrice(new anchor.BN(price), new anchor.BN(confidence), EXPONENT, new anchor.BN(publishTime), STATUS_TRADING)
+      .accounts({ feed })
+      .rpc();
+
+  const createMarket = async (duration: number) => {
+    const prediction = predictionPda(nextId++);
+    await program.methods
+      .createPrediction("Will BTC close above the threshold?", new anchor.BN(duration), ["crypto"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      })
+      .rpc();
+    return prediction;
+  };
+
+  const bindFeed = (
+    prediction: anchor.web3.PublicKey,
+    feed: anchor.web3.PublicKey,
+    threshold: number,
+    authority: anchor.web3.Keypair | null = null
+  ) => {
+    const builder = program.methods
+      .setPriceFeed(FEED_OPERATOR_GREATER_THAN, new anchor.BN(threshold), EXPONENT, new anchor.BN(60), 100)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        priceFeed: feed,
+        authority: authority ? authority.publicKey : wallet,
+        role: roleFor(authority ? authority.publicKey : wallet),
+      });
+    return authority ? builder.signers([authority]).rpc() : builder.rpc();
+  };
+
+  // Creates a binary market that ends after `duration` seconds and resolves
+  // true when the feed prints above `threshold`.
+  const createFeedMarket = async (feed: anchor.web3.PublicKey, threshold: number, duration = 2) => {
+    const prediction = await createMarket(duration);
+    await bindFeed(prediction, feed, threshold);
+    return prediction;
+  };
+
+  const setGlobalPause = (paused: boolean) =>
+    program.methods
+      .setGlobalPause(paused)
+      .accounts({ marketState: marketState.publicKey, guardian: wallet })
+      .rpc();
+
+  const resolveFromFeed = (prediction: anchor.web3.PublicKey, priceFeed: anchor.web3.PublicKey) =>
+    program.methods
+      .resolveFromFeed()
+      .accounts({ marketState: marketState.publicKey, prediction, priceFeed })
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  before(async () => {
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+
+    await program.methods
+      .setRoles(wallet, ROLE_CREATOR | ROLE_RESOLVER)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    await program.methods
+      .setRoles(creatorOnly.publicKey, ROLE_CREATOR)
+      .accounts({
+        marketState: marketState.publicKey,
+        role: roleFor(creatorOnly.publicKey),
+        admin: wallet,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .setOracleProgram(mockFeed.programId)
+      .accounts({ marketState: marketState.publicKey, admin: wallet })
+      .rpc();
+  });
+
+  it("resolves true when the price settles above the threshold", async () => {
+    const feed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await expectError(resolveFromFeed(prediction, feed), "PredictionNotEnded");
+
+    await sleep(3000);
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    await resolveFromFeed(prediction, feed);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.deepEqual(account.result, { true: {} });
+    assert.deepEqual(account.state, { resolved: {} });
+  });
+
+  it("resolves false when the price settles below the threshold", async () => {
+    const feed = await createFeed();
+    await publish(feed, 55_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await sleep(3000);
+    await publish(feed, 55_000_00000000, 10_00000000, await chainTime());
+    await resolveFromFeed(prediction, feed);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.deepEqual(account.result, { false: {} });
+  });
+
+  it("rejects settlement while the marketplace is paused", async () => {
+    const feed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await sleep(3000);
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    await setGlobalPause(true);
+    await expectError(resolveFromFeed(prediction, feed), "MarketPaused");
+
+    await setGlobalPause(false);
+    await resolveFromFeed(prediction, feed);
+    const account = await program.account.prediction.fetch(prediction);
+    assert.deepEqual(account.result, { true: {} });
+  });
+
+  it("rejects a price published before the market ended", async () => {
+    const feed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await sleep(3000);
+    await expectError(resolveFromFeed(prediction, feed), "FeedStale");
+  });
+
+  it("rejects a price with a wide confidence interval", async () => {
+    const feed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await sleep(3000);
+    // 100 bps of 65,000 is 650, so a 1,000 confidence is too wide
+    await publish(feed, 65_000_00000000, 1_000_00000000, await chainTime());
+    await expectError(resolveFromFeed(prediction, feed), "FeedConfidenceTooWide");
+  });
+
+  it("rejects a feed other than the one pinned to the market", async () => {
+    const feed = await createFeed();
+    const otherFeed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createFeedMarket(feed, 60_000_00000000);
+
+    await sleep(3000);
+    await publish(otherFeed, 65_000_00000000, 10_00000000, await chainTime());
+    await expectError(resolveFromFeed(prediction, otherFeed), "InvalidPriceFeed");
+  });
+
+  it("rejects binding a feed that the oracle program does not own", async () => {
+    const foreignFeed = await createFeed(SystemProgram.programId);
+    const prediction = await createMarket(60);
+    await expectError(bindFeed(prediction, foreignFeed, 60_000_00000000), "InvalidPriceFeed");
+  });
+
+  it("rejects binding a feed without the resolver role", async () => {
+    const feed = await createFeed();
+    await publish(feed, 65_000_00000000, 10_00000000, await chainTime());
+    const prediction = await createMarket(60);
+    await expectError(bindFeed(prediction, feed, 60_000_00000000, creatorOnly), "NotAuthorized");
+  });
+});
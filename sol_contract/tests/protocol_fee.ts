@@ -0,0 +1,227 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+// Creator, resolver, pauser and treasurer
+const ALL_ROLES = 0b1111;
+// Matches DEFAULT_PROTOCOL_FEE_BPS and MAX_PROTOCOL_FEE_BPS
+const DEFAULT_FEE_BPS = 500;
+const MAX_FEE_BPS = 1_000;
+
+describe("protocol_fee", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const connection = provider.connection;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const treasury = Keypair.generate();
+  const yesBettor = Keypair.generate();
+  const noBettor = Keypair.generate();
+  const stranger = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const userPredictionPda = (prediction: anchor.web3.PublicKey, user: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_prediction"), prediction.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  const tokenAccounts = {
+    collateralMint: null,
+    tokenVault: null,
+    tokenProgram: null,
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const airdrop = async (key: anchor.web3.PublicKey) => {
+    const signature = await connection.requestAirdrop(key, 10 * LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(signature);
+  };
+
+  const createMarket = async () => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will the airline merge?", new anchor.BN(3600), ["business"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        ...tokenAccounts,
+      })
+      .rpc();
+    nextId++;
+    return prediction;
+  };
+
+  const predict = (prediction: anchor.web3.PublicKey, user: anchor.web3.Keypair, verdict: boolean) =>
+    program.methods
+      .predict(verdict, new anchor.BN(LAMPORTS_PER_SOL))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        user: user.publicKey,
+        userPrediction: userPredictionPda(prediction, user.publicKey),
+        systemProgram: SystemProgram.programId,
+        userTokenAccount: null,
+        userAccount: null,
+        ...tokenAccounts,
+      })
+      .signers([user])
+      .rpc();
+
+  const setProtocolFee = (feeBps: number, feeRecipient: anchor.web3.PublicKey, admin: anchor.web3.Keypair | null = null) => {
+    const builder = program.methods
+      .setProtocolFee(feeBps, feeRecipient)
+      .accounts({ marketState: marketState.publicKey, admin: admin ? admin.publicKey : wallet });
+    return admin ? builder.signers([admin]).rpc() : builder.rpc();
+  };
+
+  const setPredictionFee = (prediction: anchor.web3.PublicKey, feeBps: number) =>
+    program.methods
+      .setPredictionFee(feeBps)
+      .accounts({ marketState: marketState.publicKey, prediction, admin: wallet })
+      .rpc();
+
+  const resolve = (prediction: anchor.web3.PublicKey) =>
+    program.methods
+      .resolvePrediction({ true: {} })
+      .accounts({ marketState: marketState.publicKey, prediction, authority: wallet, role: rolePda, orderBook: null })
+      .rpc();
+
+  const distribute = (prediction: anchor.web3.PublicKey, feeRecipient: anchor.web3.PublicKey) =>
+    program.methods
+      .distributeRewards()
+      .accounts({
+        prediction,
+        vault: vaultPda(prediction),
+        marketState: marketState.publicKey,
+        feeRecipient,
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        feeTokenAccount: null,
+        ...tokenAccounts,
+      })
+      .rpc();
+
+  before(async () => {
+    await airdrop(yesBettor.publicKey);
+    await airdrop(noBettor.publicKey);
+    await airdrop(stranger.publicKey);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ALL_ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+  });
+
+  describe("set_protocol_fee", () => {
+    it("rejects a fee above the cap", async () => {
+      await expectError(setProtocolFee(MAX_FEE_BPS + 1, treasury.publicKey), "InvalidFee");
+    });
+
+    it("rejects an unset fee recipient", async () => {
+      await expectError(setProtocolFee(200, PublicKey.default), "InvalidFeeRecipient");
+    });
+
+    it("rejects changes from anyone but the admin", async () => {
+      await expectError(setProtocolFee(200, stranger.publicKey, stranger), "NotAuthorized");
+    });
+
+    it("applies to new markets while running ones keep their snapshot", async () => {
+      const running = await createMarket();
+      await setProtocolFee(200, treasury.publicKey);
+      const fresh = await createMarket();
+
+      const state = await program.account.marketState.fetch(marketState.publicKey);
+      assert.equal(state.protocolFeeBps, 200);
+      assert.ok(state.feeRecipient.equals(treasury.publicKey));
+      assert.equal((await program.account.prediction.fetch(running)).protocolFeeBps, DEFAULT_FEE_BPS);
+      assert.equal((await program.account.prediction.fetch(fresh)).protocolFeeBps, 200);
+
+      // The snapshot, not the current setting, is what gets charged
+      await predict(running, yesBettor, true);
+      await predict(running, noBettor, false);
+      await resolve(running);
+      await expectError(distribute(running, stranger.publicKey), "InvalidFeeRecipient");
+
+      const before = await connection.getBalance(treasury.publicKey);
+      await distribute(running, treasury.publicKey);
+      const fee = (2 * LAMPORTS_PER_SOL * DEFAULT_FEE_BPS) / 10_000;
+      assert.equal(await connection.getBalance(treasury.publicKey), before + fee);
+    });
+  });
+
+  describe("set_prediction_fee", () => {
+    it("overrides the fee of a market before it takes stakes", async () => {
+      const prediction = await createMarket();
+      await setPredictionFee(prediction, MAX_FEE_BPS);
+      assert.equal((await program.account.prediction.fetch(prediction)).protocolFeeBps, MAX_FEE_BPS);
+
+      await predict(prediction, yesBettor, true);
+      await predict(prediction, noBettor, false);
+      await resolve(prediction);
+      const before = await connection.getBalance(treasury.publicKey);
+      await distribute(prediction, treasury.publicKey);
+      assert.equal(
+        await connection.getBalance(treasury.publicKey),
+        before + (2 * LAMPORTS_PER_SOL * MAX_FEE_BPS) / 10_000
+      );
+    });
+
+    it("rejects an override above the cap", async () => {
+      const prediction = await createMarket();
+      await expectError(setPredictionFee(prediction, MAX_FEE_BPS + 1), "InvalidFee");
+    });
+
+    it("rejects an override once the market holds stakes", async () => {
+      const prediction = await createMarket();
+      await predict(prediction, yesBettor, true);
+      await expectError(setPredictionFee(prediction, 0), "MarketAlreadyFunded");
+    });
+  });
+});
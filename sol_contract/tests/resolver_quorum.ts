@@ -0,0 +1,201 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram } = anchor.web3;
+
+// Creator and resolver
+const ROLES = 0b0011;
+
+describe("resolver_quorum", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const resolvers = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+  const outsider = Keypair.generate();
+  let nextId = 0;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const quorumPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("quorum"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = PublicKey.findProgramAddressSync(
+    [Buffer.from("role"), marketState.publicKey.toBuffer(), wallet.toBuffer()],
+    program.programId
+  )[0];
+
+  type PredictionResult = anchor.IdlTypes<SolContract>["PredictionResult"];
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  // Opens a market settled by two of the three resolvers
+  const createQuorumMarket = async (duration: number) => {
+    const prediction = predictionPda(nextId);
+    await program.methods
+      .createPrediction("Will the treaty be ratified?", new anchor.BN(duration), ["politics"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      })
+      .rpc();
+    nextId++;
+
+    await program.methods
+      .initializeResolverQuorum(
+        resolvers.map((resolver) => resolver.publicKey),
+        2
+      )
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        quorum: quorumPda(prediction),
+        authority: wallet,
+        role: rolePda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return prediction;
+  };
+
+  const vote = (
+    prediction: anchor.web3.PublicKey,
+    resolver: anchor.web3.Keypair,
+    result: PredictionResult
+  ) =>
+    program.methods
+      .submitResolverVote(result)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        quorum: quorumPda(prediction),
+        resolver: resolver.publicKey,
+      })
+      .signers([resolver])
+      .rpc();
+
+  const resolve = (prediction: anchor.web3.PublicKey, result: PredictionResult) =>
+    program.methods
+      .resolvePrediction(result)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        authority: wallet,
+        role: rolePda,
+        orderBook: null,
+      })
+      .rpc();
+
+  const setGlobalPause = (paused: boolean) =>
+    program.methods
+      .setGlobalPause(paused)
+      .accounts({ marketState: marketState.publicKey, guardian: wallet })
+      .rpc();
+
+  let agreed: anchor.web3.PublicKey;
+  let split: anchor.web3.PublicKey;
+
+  before(async () => {
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await program.methods
+      .setRoles(wallet, ROLES)
+      .accounts({ marketState: marketState.publicKey, role: rolePda, admin: wallet, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    agreed = await createQuorumMarket(2);
+    split = await createQuorumMarket(2);
+    await sleep(3000);
+  });
+
+  it("rejects votes before the market ends", async () => {
+    const open = await createQuorumMarket(3600);
+    await expectError(vote(open, resolvers[0], { true: {} }), "PredictionNotEnded");
+  });
+
+  it("rejects a vote from outside the quorum", async () => {
+    await expectError(vote(agreed, outsider, { true: {} }), "NotAuthorized");
+  });
+
+  it("rejects a second vote from the same resolver", async () => {
+    await vote(agreed, resolvers[0], { true: {} });
+    await expectError(vote(agreed, resolvers[0], { true: {} }), "AlreadyVoted");
+
+    const account = await program.account.prediction.fetch(agreed);
+    assert.deepEqual(account.state, { active: {} });
+  });
+
+  it("rejects votes while the marketplace is paused", async () => {
+    await setGlobalPause(true);
+    await expectError(vote(agreed, resolvers[1], { true: {} }), "MarketPaused");
+    await setGlobalPause(false);
+  });
+
+  it("settles once the threshold of matching votes is in", async () => {
+    await vote(agreed, resolvers[1], { true: {} });
+
+    const account = await program.account.prediction.fetch(agreed);
+    assert.deepEqual(account.state, { resolved: {} });
+    assert.deepEqual(account.result, { true: {} });
+
+    const quorum = await program.account.resolverQuorum.fetch(quorumPda(agreed));
+    assert.equal(quorum.votes.length, 2);
+  });
+
+  it("rejects settling a quorum market by hand before the resolvers split", async () => {
+    await expectError(resolve(split, { true: {} }), "QuorumRequired");
+  });
+
+  it("marks a split vote disputed and leaves it to a resolver", async () => {
+    await vote(split, resolvers[0], { true: {} });
+    await vote(split, resolvers[2], { false: {} });
+
+    let account = await program.account.prediction.fetch(split);
+    assert.deepEqual(account.state, { disputed: {} });
+
+    await resolve(split, { false: {} });
+    account = await program.account.prediction.fetch(split);
+    assert.deepEqual(account.result, { false: {} });
+  });
+});
@@ -0,0 +1,246 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { SolContract } from "../target/types/sol_contract";
+
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
+const ROLE_CREATOR = 1;
+const ROLE_PAUSER = 4;
+// Creator, resolver, pauser and treasurer
+const ALL_ROLES = 0b1111;
+
+describe("roles", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SolContract as Program<SolContract>;
+  const wallet = provider.wallet.publicKey;
+
+  const marketState = Keypair.generate();
+  const operator = Keypair.generate();
+  const newAdmin = Keypair.generate();
+  const stranger = Keypair.generate();
+  let prediction: anchor.web3.PublicKey;
+
+  const predictionPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        marketState.publicKey.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = (authority: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), marketState.publicKey.toBuffer(), authority.toBuffer()],
+      program.programId
+    )[0];
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const airdrop = async (key: anchor.web3.PublicKey) => {
+    const signature = await provider.connection.requestAirdrop(key, 10 * LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+  };
+
+  const setRoles = (
+    authority: anchor.web3.PublicKey,
+    roles: number,
+    admin: anchor.web3.Keypair | null = null
+  ) => {
+    const builder = program.methods
+      .setRoles(authority, roles)
+      .accounts({
+        marketState: marketState.publicKey,
+        role: rolePda(authority),
+        admin: admin ? admin.publicKey : wallet,
+        systemProgram: SystemProgram.programId,
+      });
+    return admin ? builder.signers([admin]).rpc() : builder.rpc();
+  };
+
+  const pauseAs = (authority: anchor.web3.Keypair) =>
+    program.methods
+      .pausePrediction()
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        authority: authority.publicKey,
+        role: rolePda(authority.publicKey),
+      })
+      .signers([authority])
+      .rpc();
+
+  const resume = () =>
+    program.methods
+      .resumePrediction(false)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        authority: wallet,
+        role: rolePda(wallet),
+      })
+      .rpc();
+
+  const acceptAdmin = (pendingAdmin: anchor.web3.Keypair) =>
+    program.methods
+      .acceptAdmin()
+      .accounts({ marketState: marketState.publicKey, pendingAdmin: pendingAdmin.publicKey })
+      .signers([pendingAdmin])
+      .rpc();
+
+  before(async () => {
+    await airdrop(operator.publicKey);
+    await airdrop(newAdmin.publicKey);
+    await airdrop(stranger.publicKey);
+
+    await program.methods
+      .initialize()
+      .accounts({ marketState: marketState.publicKey, admin: wallet, systemProgram: SystemProgram.programId })
+      .signers([marketState])
+      .rpc();
+    await setRoles(wallet, ALL_ROLES);
+    await setRoles(operator.publicKey, ROLE_PAUSER);
+
+    prediction = predictionPda(0);
+    await program.methods
+      .createPrediction("Will the bridge reopen?", new anchor.BN(3600), ["roads"], 0, 2, [], null)
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: wallet,
+        role: rolePda(wallet),
+        systemProgram: SystemProgram.programId,
+        collateralMint: null,
+        tokenVault: null,
+        tokenProgram: null,
+      })
+      .rpc();
+  });
+
+  describe("role bits", () => {
+    it("lets a pauser pause", async () => {
+      await pauseAs(operator);
+      const account = await program.account.prediction.fetch(prediction);
+      assert.deepEqual(account.state, { paused: {} });
+      await resume();
+    });
+
+    it("rejects a creator-gated instruction from a pauser", async () => {
+      const next = predictionPda(1);
+      await expectError(
+        program.methods
+          .createPrediction("Will the ferry run?", new anchor.BN(3600), ["roads"], 0, 2, [], null)
+          .accounts({
+            marketState: marketState.publicKey,
+            prediction: next,
+            vault: vaultPda(next),
+            authority: operator.publicKey,
+            role: rolePda(operator.publicKey),
+            systemProgram: SystemProgram.programId,
+            collateralMint: null,
+            tokenVault: null,
+            tokenProgram: null,
+          })
+          .signers([operator])
+          .rpc(),
+        "NotAuthorized"
+      );
+    });
+
+    it("rejects a treasurer-gated instruction from a pauser", async () => {
+      await expectError(
+        program.methods
+          .withdrawFees(new anchor.BN(1))
+          .accounts({
+            marketState: marketState.publicKey,
+            authority: operator.publicKey,
+            role: rolePda(operator.publicKey),
+          })
+          .signers([operator])
+          .rpc(),
+        "NotAuthorized"
+      );
+    });
+
+    it("rejects bits outside the known roles", async () => {
+      await expectError(setRoles(operator.publicKey, 0b10000), "InvalidRole");
+    });
+
+    it("rejects role changes from anyone but the admin", async () => {
+      await expectError(setRoles(stranger.publicKey, ALL_ROLES, stranger), "NotAuthorized");
+    });
+  });
+
+  describe("revocation", () => {
+    it("rejects a pauser whose role was swapped for another", async () => {
+      await setRoles(operator.publicKey, ROLE_CREATOR);
+      await expectError(pauseAs(operator), "NotAuthorized");
+    });
+
+    it("rejects a pauser whose roles were all revoked", async () => {
+      await setRoles(operator.publicKey, ROLE_PAUSER);
+      await pauseAs(operator);
+      await resume();
+
+      await setRoles(operator.publicKey, 0);
+      const role = await program.account.role.fetch(rolePda(operator.publicKey));
+      assert.equal(role.roles, 0);
+      await expectError(pauseAs(operator), "NotAuthorized");
+    });
+  });
+
+  describe("admin transfer", () => {
+    it("rejects accepting when no transfer is pending", async () => {
+      await expectError(acceptAdmin(newAdmin), "NoPendingAdmin");
+    });
+
+    it("rejects a proposal from anyone but the admin", async () => {
+      await expectError(
+        program.methods
+          .proposeAdmin(stranger.publicKey)
+          .accounts({ marketState: marketState.publicKey, admin: stranger.publicKey })
+          .signers([stranger])
+          .rpc(),
+        "NotAuthorized"
+      );
+    });
+
+    it("rejects acceptance by a key that is not pending", async () => {
+      await program.methods
+        .proposeAdmin(newAdmin.publicKey)
+        .accounts({ marketState: marketState.publicKey, admin: wallet })
+        .rpc();
+
+      await expectError(acceptAdmin(stranger), "NotAuthorized");
+    });
+
+    it("hands admin rights to the pending key", async () => {
+      await acceptAdmin(newAdmin);
+
+      const state = await program.account.marketState.fetch(marketState.publicKey);
+      assert.ok(state.admin.equals(newAdmin.publicKey));
+      assert.ok(state.pendingAdmin.equals(PublicKey.default));
+
+      await expectError(setRoles(operator.publicKey, ROLE_PAUSER), "NotAuthorized");
+      await setRoles(operator.publicKey, ROLE_PAUSER, newAdmin);
+    });
+  });
+});
@@ -1,16 +1,458 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { assert } from "chai";
 import { SolContract } from "../target/types/sol_contract";
 
+const { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } = anchor.web3;
+
 describe("sol_contract", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.SolContract as Program<SolContract>;
 
+  const marketState = Keypair.generate();
+  const attackerMarketState = Keypair.generate();
+  const attacker = Keypair.generate();
+  const bettor = Keypair.generate();
+
+  const predictionPda = (market: anchor.web3.PublicKey, id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("prediction"),
+        market.toBuffer(),
+        new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const vaultPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const userPredictionPda = (
+    prediction: anchor.web3.PublicKey,
+    user: anchor.web3.PublicKey
+  ) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_prediction"), prediction.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const claimsPda = (prediction: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("claims"), prediction.toBuffer()],
+      program.programId
+    )[0];
+
+  const rolePda = (market: anchor.web3.PublicKey, authority: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), market.toBuffer(), authority.toBuffer()],
+      program.programId
+    )[0];
+
+  // Creator, resolver, pauser and treasurer
+  const ALL_ROLES = 0b1111;
+
+  const tokenAccounts = {
+    collateralMint: null,
+    tokenVault: null,
+    tokenProgram: null,
+  };
+
+  const createPrediction = (
+    market: anchor.web3.PublicKey,
+    prediction: anchor.web3.PublicKey,
+    admin: anchor.web3.Keypair | null = null
+  ) => {
+    const builder = program.methods
+      .createPrediction("Will it rain on Tuesday?", new anchor.BN(3600), ["weather"], 0, 2, [], null)
+      .accounts({
+        marketState: market,
+        prediction,
+        vault: vaultPda(prediction),
+        authority: admin ? admin.publicKey : provider.wallet.publicKey,
+        role: rolePda(market, admin ? admin.publicKey : provider.wallet.publicKey),
+        systemProgram: SystemProgram.programId,
+        ...tokenAccounts,
+      });
+    return admin ? builder.signers([admin]).rpc() : builder.rpc();
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+    }
+  };
+
+  const airdrop = async (key: anchor.web3.PublicKey) => {
+    const signature = await provider.connection.requestAirdrop(key, 10 * LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+  };
+
+  before(async () => {
+    await airdrop(attacker.publicKey);
+    await airdrop(bettor.publicKey);
+  });
+
   it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
+    const tx = await program.methods
+      .initialize()
+      .accounts({
+        marketState: marketState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([marketState])
+      .rpc();
     console.log("Your transaction signature", tx);
+
+    await program.methods
+      .initialize()
+      .accounts({
+        marketState: attackerMarketState.publicKey,
+        admin: attacker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([attackerMarketState, attacker])
+      .rpc();
+
+    await program.methods
+      .setRoles(provider.wallet.publicKey, ALL_ROLES)
+      .accounts({
+        marketState: marketState.publicKey,
+        role: rolePda(marketState.publicKey, provider.wallet.publicKey),
+        admin: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .setRoles(attacker.publicKey, ALL_ROLES)
+      .accounts({
+        marketState: attackerMarketState.publicKey,
+        role: rolePda(attackerMarketState.publicKey, attacker.publicKey),
+        admin: attacker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([attacker])
+      .rpc();
+  });
+
+  it("creates predictions at the market PDA", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await createPrediction(marketState.publicKey, prediction);
+
+    const account = await program.account.prediction.fetch(prediction);
+    assert.ok(account.market.equals(marketState.publicKey));
+
+    await createPrediction(attackerMarketState.publicKey, predictionPda(attackerMarketState.publicKey, 0), attacker);
+  });
+
+  it("rejects a prediction account that is not the market PDA", async () => {
+    const prediction = Keypair.generate().publicKey;
+    await expectError(createPrediction(marketState.publicKey, prediction), "ConstraintSeeds");
+  });
+
+  it("rejects predicting through a spoofed market", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await expectError(
+      program.methods
+        .predict(true, new anchor.BN(LAMPORTS_PER_SOL))
+        .accounts({
+          marketState: attackerMarketState.publicKey,
+          prediction,
+          vault: vaultPda(prediction),
+          user: bettor.publicKey,
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          systemProgram: SystemProgram.programId,
+          userTokenAccount: null,
+          userAccount: null,
+          ...tokenAccounts,
+        })
+        .signers([bettor])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects staking into another prediction's vault", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    const foreignVault = vaultPda(predictionPda(attackerMarketState.publicKey, 0));
+    await expectError(
+      program.methods
+        .predict(true, new anchor.BN(LAMPORTS_PER_SOL))
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction,
+          vault: foreignVault,
+          user: bettor.publicKey,
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          systemProgram: SystemProgram.programId,
+          userTokenAccount: null,
+          userAccount: null,
+          ...tokenAccounts,
+        })
+        .signers([bettor])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects resolving a prediction with a foreign market admin", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await expectError(
+      program.methods
+        .resolvePrediction({ true: {} })
+        .accounts({
+          marketState: attackerMarketState.publicKey,
+          prediction,
+          authority: attacker.publicKey,
+          role: rolePda(attackerMarketState.publicKey, attacker.publicKey),
+          orderBook: null,
+        })
+        .signers([attacker])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects claiming through a spoofed market", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await program.methods
+      .predict(true, new anchor.BN(LAMPORTS_PER_SOL))
+      .accounts({
+        marketState: marketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        user: bettor.publicKey,
+        userPrediction: userPredictionPda(prediction, bettor.publicKey),
+        systemProgram: SystemProgram.programId,
+        userTokenAccount: null,
+        userAccount: null,
+        ...tokenAccounts,
+      })
+      .signers([bettor])
+      .rpc();
+
+    await expectError(
+      program.methods
+        .claimReward()
+        .accounts({
+          marketState: attackerMarketState.publicKey,
+          prediction,
+          vault: vaultPda(prediction),
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+          userTokenAccount: null,
+          ...tokenAccounts,
+        })
+        .signers([bettor])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("initializes a claims queue per prediction", async () => {
+    for (const prediction of [
+      predictionPda(marketState.publicKey, 0),
+      predictionPda(attackerMarketState.publicKey, 0),
+    ]) {
+      await program.methods
+        .initializeClaims()
+        .accounts({
+          prediction,
+          claims: claimsPda(prediction),
+          admin: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+  });
+
+  it("rejects submitting a claim into another prediction's claims queue", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await expectError(
+      program.methods
+        .submitClaim()
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction,
+          claims: claimsPda(predictionPda(attackerMarketState.publicKey, 0)),
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects submitting a claim with another prediction's position", async () => {
+    const foreignPrediction = predictionPda(attackerMarketState.publicKey, 0);
+    await expectError(
+      program.methods
+        .submitClaim()
+        .accounts({
+          marketState: attackerMarketState.publicKey,
+          prediction: foreignPrediction,
+          claims: claimsPda(foreignPrediction),
+          userPrediction: userPredictionPda(predictionPda(marketState.publicKey, 0), bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+  });
+
+  // The attacker is treasurer of their own market and points one of the
+  // accounts at the honest market instead
+  const approveClaims = (overrides: { [name: string]: anchor.web3.PublicKey }) => {
+    const prediction = predictionPda(attackerMarketState.publicKey, 0);
+    return program.methods
+      .approveClaims([new anchor.BN(0)])
+      .accounts({
+        marketState: attackerMarketState.publicKey,
+        prediction,
+        vault: vaultPda(prediction),
+        claims: claimsPda(prediction),
+        authority: attacker.publicKey,
+        role: rolePda(attackerMarketState.publicKey, attacker.publicKey),
+        systemProgram: SystemProgram.programId,
+        ...overrides,
+      })
+      .remainingAccounts([{ pubkey: attacker.publicKey, isSigner: false, isWritable: true }])
+      .signers([attacker])
+      .rpc();
+  };
+
+  it("rejects approving claims on a prediction of another market", async () => {
+    const prediction = predictionPda(marketState.publicKey, 0);
+    await expectError(
+      approveClaims({ prediction, vault: vaultPda(prediction), claims: claimsPda(prediction) }),
+      "ConstraintSeeds"
+    );
+  });
+
+  it("rejects approving claims out of another prediction's vault", async () => {
+    const foreignVault = vaultPda(predictionPda(marketState.publicKey, 0));
+    await expectError(approveClaims({ vault: foreignVault }), "ConstraintSeeds");
+  });
+
+  it("rejects approving claims from another prediction's claims queue", async () => {
+    const foreignClaims = claimsPda(predictionPda(marketState.publicKey, 0));
+    await expectError(approveClaims({ claims: foreignClaims }), "ConstraintSeeds");
+  });
+
+  describe("cancel_bet", () => {
+    const PENALTY_BPS = 1_000;
+
+    const nextPrediction = async () => {
+      const state = await program.account.marketState.fetch(marketState.publicKey);
+      return predictionPda(marketState.publicKey, state.nextPredictionId.toNumber());
+    };
+
+    const setCancelPolicy = (penaltyBps: number, cutoff: number) =>
+      program.methods
+        .setCancelPolicy(penaltyBps, new anchor.BN(cutoff))
+        .accounts({ marketState: marketState.publicKey, admin: provider.wallet.publicKey })
+        .rpc();
+
+    const stake = (prediction: anchor.web3.PublicKey, verdict: boolean, amount: number) =>
+      program.methods
+        .predict(verdict, new anchor.BN(amount))
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction,
+          vault: vaultPda(prediction),
+          user: bettor.publicKey,
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          systemProgram: SystemProgram.programId,
+          userTokenAccount: null,
+          userAccount: null,
+          ...tokenAccounts,
+        })
+        .signers([bettor])
+        .rpc();
+
+    const cancelBet = (prediction: anchor.web3.PublicKey, outcome: number, amount: number) =>
+      program.methods
+        .cancelBet(outcome, new anchor.BN(amount))
+        .accounts({
+          marketState: marketState.publicKey,
+          prediction,
+          vault: vaultPda(prediction),
+          userPrediction: userPredictionPda(prediction, bettor.publicKey),
+          user: bettor.publicKey,
+          systemProgram: SystemProgram.programId,
+          userTokenAccount: null,
+          ...tokenAccounts,
+        })
+        .signers([bettor])
+        .rpc();
+
+    after(() => setCancelPolicy(0, 0));
+
+    it("refunds a partial cancellation minus the penalty and keeps the penalty in the pool", async () => {
+      await setCancelPolicy(PENALTY_BPS, 60);
+      const prediction = await nextPrediction();
+      await createPrediction(marketState.publicKey, prediction);
+      await stake(prediction, true, LAMPORTS_PER_SOL);
+
+      const before = await provider.connection.getBalance(bettor.publicKey);
+      await cancelBet(prediction, 0, 0.4 * LAMPORTS_PER_SOL);
+      const after = await provider.connection.getBalance(bettor.publicKey);
+
+      // 10% of 0.4 SOL stays behind
+      assert.equal(after - before, 0.36 * LAMPORTS_PER_SOL);
+      const account = await program.account.prediction.fetch(prediction);
+      assert.equal(account.yesAmount.toNumber(), 0.6 * LAMPORTS_PER_SOL);
+      assert.equal(account.totalAmount.toNumber(), 0.64 * LAMPORTS_PER_SOL);
+      assert.equal(account.refundBasis.toNumber(), 0.6 * LAMPORTS_PER_SOL);
+      assert.equal(account.yesVotes.toNumber(), 1);
+      const position = await program.account.userPrediction.fetch(
+        userPredictionPda(prediction, bettor.publicKey)
+      );
+      assert.equal(position.yesAmount.toNumber(), 0.6 * LAMPORTS_PER_SOL);
+      assert.equal(position.amount.toNumber(), 0.6 * LAMPORTS_PER_SOL);
+
+      // Cancelling the rest also takes the bettor out of the vote counts
+      await cancelBet(prediction, 0, 0.6 * LAMPORTS_PER_SOL);
+      const emptied = await program.account.prediction.fetch(prediction);
+      assert.equal(emptied.yesAmount.toNumber(), 0);
+      assert.equal(emptied.totalAmount.toNumber(), 0.1 * LAMPORTS_PER_SOL);
+      assert.equal(emptied.yesVotes.toNumber(), 0);
+      assert.equal(emptied.totalVotes.toNumber(), 0);
+    });
+
+    it("rejects cancelling more than the stake on that side", async () => {
+      await setCancelPolicy(PENALTY_BPS, 60);
+      const prediction = await nextPrediction();
+      await createPrediction(marketState.publicKey, prediction);
+      await stake(prediction, true, LAMPORTS_PER_SOL);
+
+      await expectError(cancelBet(prediction, 0, 2 * LAMPORTS_PER_SOL), "InsufficientStake");
+      await expectError(cancelBet(prediction, 1, LAMPORTS_PER_SOL), "InsufficientStake");
+    });
+
+    it("rejects cancelling once the cutoff before the end has been reached", async () => {
+      // The market lasts an hour, so an hour-long cutoff closes the window at once
+      await setCancelPolicy(PENALTY_BPS, 3600);
+      const prediction = await nextPrediction();
+      await createPrediction(marketState.publicKey, prediction);
+      await stake(prediction, true, LAMPORTS_PER_SOL);
+
+      await expectError(cancelBet(prediction, 0, LAMPORTS_PER_SOL), "CancelWindowClosed");
+    });
   });
 });